@@ -386,7 +386,15 @@
 
 // Starting with your given initial configuration, simulate six cycles in a 4-dimensional space. How many cubes are left in the active state after the sixth cycle?
 
-use std::{collections::HashMap, fs};
+use animation::Frame;
+use std::{collections::HashMap, env, error::Error, fs, fs::File, io, time::Duration};
+
+const DELAY: Duration = Duration::from_millis(400);
+const GIF_SCALE: usize = 10;
+
+// brightness of active and inactive cubes in a slice
+const ACTIVE_LEVEL: u8 = 255;
+const INACTIVE_LEVEL: u8 = 40;
 
 type X = i32;
 type Y = i32;
@@ -493,18 +501,117 @@ impl ConwayDimension {
 
         count
     }
+
+    // smallest and largest x, y of any active cube
+    fn bounds(&self) -> Option<((X, Y), (X, Y))> {
+        let xs = self.cubes.keys().map(|Cube(x, _, _, _)| *x);
+        let ys = self.cubes.keys().map(|Cube(_, y, _, _)| *y);
+
+        Some((
+            (xs.clone().min()?, ys.clone().min()?),
+            (xs.max()?, ys.max()?),
+        ))
+    }
+
+    // the x/y plane at the given z and w, rows from min y to max y
+    fn slice(&self, z: Z, w: W, (min, max): ((X, Y), (X, Y))) -> Result<Frame, Box<dyn Error>> {
+        let levels = (min.1..=max.1)
+            .flat_map(|y| (min.0..=max.0).map(move |x| Cube(x, y, z, w)))
+            .map(|cube| {
+                if self.has_alive_cube(&cube) {
+                    ACTIVE_LEVEL
+                } else {
+                    INACTIVE_LEVEL
+                }
+            })
+            .collect();
+        let width = (max.0 - min.0 + 1) as usize;
+        let height = (max.1 - min.1 + 1) as usize;
+
+        Ok(Frame::new(width, height, levels)?)
+    }
 }
 
-fn main() {
-    let mut c = ConwayDimension::new(
-        &fs::read_to_string("src/input.txt")
-            .unwrap()
-            .trim_end_matches("\n"),
-    );
+// cargo run -- [cycles] [--animate] [--slice z,w] [--gif out.gif]
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut cycles = 6;
+    let mut animate = false;
+    let mut slice = (0, 0);
+    let mut gif_path = None;
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--animate" => animate = true,
+            "--gif" => gif_path = Some(args.next().ok_or("--gif needs a path")?),
+            "--slice" => {
+                let zw = args.next().ok_or("--slice needs z,w")?;
+                let (z, w) = zw.split_once(',').ok_or("--slice needs z,w")?;
+
+                slice = (z.trim().parse()?, w.trim().parse()?);
+            }
+            n => cycles = n.parse()?,
+        }
+    }
+
+    let mut c = ConwayDimension::new(fs::read_to_string("src/input.txt")?.trim_end_matches('\n'));
 
-    for _ in 0..6 {
+    // only keep every cycle around when something is going to draw them
+    let mut history = if animate || gif_path.is_some() {
+        Some(vec![c.cubes.clone()])
+    } else {
+        None
+    };
+
+    for _ in 0..cycles {
         c.cycle();
+
+        if let Some(history) = history.as_mut() {
+            history.push(c.cubes.clone());
+        }
+    }
+
+    if let Some(history) = history {
+        // frame every cycle with the bounds of the final, largest one so the view doesn't jump
+        let dimensions: Vec<ConwayDimension> = history
+            .into_iter()
+            .map(|cubes| ConwayDimension { cubes })
+            .collect();
+        let bounds = dimensions
+            .iter()
+            .filter_map(ConwayDimension::bounds)
+            .reduce(|(min, max), (lo, hi)| {
+                (
+                    (min.0.min(lo.0), min.1.min(lo.1)),
+                    (max.0.max(hi.0), max.1.max(hi.1)),
+                )
+            })
+            .unwrap_or(((0, 0), (0, 0)));
+        let frames = dimensions
+            .iter()
+            .map(|dimension| dimension.slice(slice.0, slice.1, bounds))
+            .collect::<Result<Vec<Frame>, _>>()?;
+
+        if let Some(path) = gif_path {
+            animation::write_gif(&frames, GIF_SCALE, DELAY, File::create(path)?)?;
+        }
+
+        if animate {
+            let captions = dimensions.iter().enumerate().map(|(cycle, dimension)| {
+                format!(
+                    "cycle {}, z={}, w={}: {} active",
+                    cycle,
+                    slice.0,
+                    slice.1,
+                    dimension.cubes.len()
+                )
+            });
+
+            animation::play(captions.zip(frames), DELAY, &mut io::stdout())?;
+        }
     }
 
     println!("{:#?}", c.cubes.keys().count());
+
+    Ok(())
 }
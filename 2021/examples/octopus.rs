@@ -0,0 +1,58 @@
+// Plays the day 11 octopus simulation in the terminal, optionally saving it as a GIF.
+//
+//   cargo run --example octopus -- [steps] [--wrap] [--gif out.gif]
+
+use animation::Frame;
+use solomonhawk::day11::input_generator;
+use std::error::Error;
+use std::fs::File;
+use std::io;
+use std::time::Duration;
+
+const DELAY: Duration = Duration::from_millis(120);
+const GIF_SCALE: usize = 8;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut steps = 100;
+    let mut wrap = false;
+    let mut gif_path = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wrap" => wrap = true,
+            "--gif" => gif_path = Some(args.next().ok_or("--gif needs a path")?),
+            n => steps = n.parse()?,
+        }
+    }
+
+    let input = include_str!("../input/2021/day11.txt").trim();
+    let mut grid = input_generator(input)?.with_wrap(wrap);
+    let mut frames: Vec<(String, Frame)> = vec![("step 0".to_string(), grid.frame())];
+
+    for _ in 0..steps {
+        let events = grid.step();
+        let caption = format!(
+            "step {}: {} flashes in {} waves{}",
+            events.step,
+            events.flashes.len(),
+            events.waves(),
+            if events.synchronized {
+                ", synchronized!"
+            } else {
+                ""
+            }
+        );
+
+        frames.push((caption, grid.frame()));
+    }
+
+    if let Some(path) = gif_path {
+        let images: Vec<Frame> = frames.iter().map(|(_, frame)| frame.clone()).collect();
+        animation::write_gif(&images, GIF_SCALE, DELAY, File::create(path)?)?;
+    }
+
+    animation::play(frames, DELAY, &mut io::stdout())?;
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+// Draws a day 17 probe trajectory, optionally saving it as an SVG. Without a velocity it draws
+// the highest trajectory that hits the target.
+//
+//   cargo run --example trajectory -- [vx vy] [--svg out.svg]
+
+use solomonhawk::day17::{self, calculate_trajectory, input_generator};
+use std::error::Error;
+use std::fs;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut velocity = vec![];
+    let mut svg_path = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--svg" => svg_path = Some(args.next().ok_or("--svg needs a path")?),
+            n => velocity.push(n.parse::<isize>()?),
+        }
+    }
+
+    let input = include_str!("../input/2021/day17.txt").trim();
+    let target = input_generator(input)?;
+
+    let (vx, vy) = match velocity[..] {
+        [vx, vy] => (vx, vy),
+        [] => day17::solve(&target)?
+            .velocities
+            .into_iter()
+            .max_by_key(|&(vx, vy)| (calculate_trajectory(vx, vy, &target).apex().1, -vx))
+            .ok_or("No trajectory hits the target")?,
+        _ => return Err("Expected a velocity as `vx vy`".into()),
+    };
+
+    let trajectory = calculate_trajectory(vx, vy, &target);
+
+    println!("{}", day17::render_ascii(&target, &trajectory));
+    println!(
+        "launched at {},{}: {} after {} steps, apex at {:?}",
+        vx,
+        vy,
+        if trajectory.hit { "hit" } else { "missed" },
+        trajectory.positions.len() - 1,
+        trajectory.apex()
+    );
+
+    if let Some(path) = svg_path {
+        fs::write(path, day17::render_svg(&target, &trajectory))?;
+    }
+
+    Ok(())
+}
@@ -1,3 +1,13 @@
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::ops::Add;
+
+// part two compares sums of three consecutive readings
+const SONAR_WINDOW: NonZeroUsize = match NonZeroUsize::new(3) {
+    Some(size) => size,
+    None => unreachable!(),
+};
+
 #[aoc_generator(day1)]
 pub fn input_generator(input: &str) -> Vec<usize> {
     input.split("\n").map(|n| n.parse().unwrap()).collect()
@@ -10,23 +20,128 @@ pub fn part1(nums: &[usize]) -> usize {
 
 #[aoc(day1, part2)]
 pub fn part2(nums: &[usize]) -> usize {
-    count_increasing_seqs(
-        &nums
-            .windows(3)
-            .map(|ns| ns.iter().sum())
-            .collect::<Vec<usize>>(),
-    )
+    trend(windows(nums.iter().copied(), SONAR_WINDOW, Aggregate::Sum)).increases
 }
 
 pub fn count_increasing_seqs(nums: &[usize]) -> usize {
-    nums.windows(2)
-        .fold(0, |acc, slice| acc + (slice[1] > slice[0]) as usize)
+    trend(nums.iter().copied()).increases
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    fn apply<'a, T>(&self, values: impl Iterator<Item = &'a T>) -> Option<T>
+    where
+        T: Ord + Add<Output = T> + Clone + 'a,
+    {
+        let mut values = values.cloned();
+        let first = values.next()?;
+
+        Some(match self {
+            Aggregate::Sum => values.fold(first, |acc, v| acc + v),
+            Aggregate::Min => values.fold(first, Ord::min),
+            Aggregate::Max => values.fold(first, Ord::max),
+        })
+    }
+}
+
+/**
+ * Aggregates every full window of `size` consecutive readings. Only the
+ * current window is ever held in memory, so this works just as well on an
+ * endless feed as on a puzzle input.
+ */
+pub struct Windows<I: Iterator> {
+    readings: I,
+    size: usize,
+    aggregate: Aggregate,
+    window: VecDeque<I::Item>,
+}
+
+pub fn windows<I, T>(readings: I, size: NonZeroUsize, aggregate: Aggregate) -> Windows<I::IntoIter>
+where
+    I: IntoIterator<Item = T>,
+    T: Ord + Add<Output = T> + Clone,
+{
+    Windows {
+        readings: readings.into_iter(),
+        size: size.get(),
+        aggregate,
+        window: VecDeque::with_capacity(size.get()),
+    }
+}
+
+impl<I, T> Iterator for Windows<I>
+where
+    I: Iterator<Item = T>,
+    T: Ord + Add<Output = T> + Clone,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while self.window.len() < self.size {
+            self.window.push_back(self.readings.next()?);
+        }
+
+        let value = self.aggregate.apply(self.window.iter());
+
+        self.window.pop_front();
+        value
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Trend {
+    pub increases: usize,
+    pub decreases: usize,
+    pub longest_increasing: usize, // most values in a row that were each larger than the last
+    pub longest_decreasing: usize,
+}
+
+// compares each value with the one before it, holding on to nothing but the last value
+pub fn trend<T: Ord>(values: impl IntoIterator<Item = T>) -> Trend {
+    let mut trend = Trend::default();
+    let mut previous: Option<T> = None;
+    let (mut increasing, mut decreasing) = (0, 0);
+
+    for value in values {
+        match &previous {
+            Some(p) if value > *p => {
+                trend.increases += 1;
+                increasing += 1;
+                decreasing = 1;
+            }
+            Some(p) if value < *p => {
+                trend.decreases += 1;
+                increasing = 1;
+                decreasing += 1;
+            }
+            _ => {
+                increasing = 1;
+                decreasing = 1;
+            }
+        }
+
+        trend.longest_increasing = trend.longest_increasing.max(increasing);
+        trend.longest_decreasing = trend.longest_decreasing.max(decreasing);
+        previous = Some(value);
+    }
+
+    trend
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn size(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
     #[test]
     fn input() {
         assert_eq!(
@@ -55,4 +170,62 @@ mod tests {
             5
         );
     }
+
+    #[test]
+    fn aggregates() {
+        let readings = [3, 1, 4, 1, 5, 9, 2];
+
+        assert_eq!(
+            windows(readings, size(3), Aggregate::Sum).collect::<Vec<_>>(),
+            [8, 6, 10, 15, 16]
+        );
+        assert_eq!(
+            windows(readings, size(3), Aggregate::Min).collect::<Vec<_>>(),
+            [1, 1, 1, 1, 2]
+        );
+        assert_eq!(
+            windows(readings, size(2), Aggregate::Max).collect::<Vec<_>>(),
+            [3, 4, 4, 5, 9, 9]
+        );
+        assert_eq!(windows(readings, size(8), Aggregate::Sum).count(), 0);
+    }
+
+    #[test]
+    fn trends() {
+        assert_eq!(
+            trend([5, 6, 7, 7, 3, 2, 1, 0, 4]),
+            Trend {
+                increases: 3,
+                decreases: 4,
+                longest_increasing: 3,
+                longest_decreasing: 5,
+            }
+        );
+        assert_eq!(trend(Vec::<i32>::new()), Trend::default());
+    }
+
+    #[test]
+    fn unbounded_feed() {
+        // a sawtooth that never ends, only the first few windows are ever pulled
+        let feed = (0u64..).map(|n| n % 4);
+        let sums: Vec<u64> = windows(feed, size(4), Aggregate::Sum).take(100).collect();
+
+        assert!(sums.iter().all(|&sum| sum == 6));
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day1.txt");
+        let input = input_generator(input_text.trim());
+
+        assert_eq!(part1(&input), 1462);
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day1.txt");
+        let input = input_generator(input_text.trim());
+
+        assert_eq!(part2(&input), 1497);
+    }
 }
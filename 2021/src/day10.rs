@@ -1,125 +1,171 @@
+// the navigation subsystem's chunks: open, close, corruption score, completion score
+const NAVIGATION_PAIRS: [(char, char, usize, usize); 4] = [
+    ('(', ')', 3, 1),
+    ('[', ']', 57, 2),
+    ('{', '}', 1197, 3),
+    ('<', '>', 25137, 4),
+];
+
+// completion scores are read as digits in this base
+const COMPLETION_BASE: usize = 5;
+
 #[aoc_generator(day10)]
 pub fn input_generator(input: &str) -> Vec<String> {
-    input.split("\n").map(|s| s.to_string()).collect()
+    input.split('\n').map(|s| s.to_string()).collect()
 }
 
 #[aoc(day10, part1)]
 pub fn part1(lines: &[String]) -> usize {
+    let checker = Checker::navigation();
+
     lines
         .iter()
-        .map(analyze)
+        .map(|line| checker.analyze(line))
         .filter_map(AnalyzedLine::corrupted)
-        .map(score_symbol)
+        .filter_map(|corruption| checker.score_corruption(corruption.found))
         .sum()
 }
 
 #[aoc(day10, part2)]
-pub fn part2(lines: &[String]) -> usize {
+pub fn part2(lines: &[String]) -> Option<usize> {
+    let checker = Checker::navigation();
     let mut scores: Vec<_> = lines
         .iter()
-        .map(analyze)
+        .map(|line| checker.analyze(line))
         .filter_map(AnalyzedLine::incomplete)
-        .map(completion_string)
-        .map(score_completion)
+        .filter_map(|stack| checker.score_completion(&checker.completion_string(&stack)))
         .collect();
 
     scores.sort_unstable();
-    scores[scores.len() / 2]
+    scores.get(scores.len() / 2).copied()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BracketPair {
+    pub open: char,
+    pub close: char,
+    pub corruption_score: usize,
+    pub completion_score: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Corruption {
+    pub column: usize,          // 1-based, counted in chars
+    pub found: char,            // the offending closer
+    pub expected: Option<char>, // closer for the innermost open chunk, `None` if nothing was open
 }
 
 #[derive(Debug, PartialEq)]
-enum AnalyzedLine {
-    Incomplete(Vec<char>),
-    Corrupted(char),
+pub enum AnalyzedLine {
+    Incomplete(Vec<char>), // chunks still open, outermost first
+    Corrupted(Corruption),
     Complete,
 }
 
 impl AnalyzedLine {
-    fn incomplete(self) -> Option<Vec<char>> {
+    pub fn incomplete(self) -> Option<Vec<char>> {
         match self {
             AnalyzedLine::Incomplete(stack) => Some(stack),
             _ => None,
         }
     }
 
-    fn corrupted(self) -> Option<char> {
+    pub fn corrupted(self) -> Option<Corruption> {
         match self {
-            AnalyzedLine::Corrupted(last_char) => Some(last_char),
+            AnalyzedLine::Corrupted(corruption) => Some(corruption),
             _ => None,
         }
     }
 }
 
-fn analyze(line: &String) -> AnalyzedLine {
-    let mut stack = Vec::with_capacity(line.len() / 2);
-    let mut chars = line.chars();
+/**
+ * Checks nesting for any set of bracket pairs. Characters that aren't part of
+ * a pair are skipped, so the same checker works on formats with content
+ * between their delimiters.
+ */
+#[derive(Debug, Clone)]
+pub struct Checker {
+    pairs: Vec<BracketPair>,
+    completion_base: usize,
+}
 
-    while let Some(symbol) = chars.next() {
-        if is_closing_symbol(symbol) {
-            match stack.pop() {
-                Some(b) if symbol == matching_closing_symbol(b) => (),
-                Some(_) => return AnalyzedLine::Corrupted(symbol),
-                None => return AnalyzedLine::Incomplete(stack),
-            }
-        } else {
-            stack.push(symbol)
+impl Checker {
+    pub fn new(pairs: &[(char, char, usize, usize)], completion_base: usize) -> Self {
+        Checker {
+            pairs: pairs
+                .iter()
+                .map(
+                    |&(open, close, corruption_score, completion_score)| BracketPair {
+                        open,
+                        close,
+                        corruption_score,
+                        completion_score,
+                    },
+                )
+                .collect(),
+            completion_base,
         }
     }
 
-    if stack.len() > 0 {
-        AnalyzedLine::Incomplete(stack)
-    } else {
-        AnalyzedLine::Complete
+    pub fn navigation() -> Self {
+        Checker::new(&NAVIGATION_PAIRS, COMPLETION_BASE)
     }
-}
 
-fn completion_string(chars: Vec<char>) -> String {
-    let mut completion = String::with_capacity(chars.len());
+    pub fn analyze(&self, line: &str) -> AnalyzedLine {
+        let mut stack = Vec::with_capacity(line.len() / 2);
+
+        for (i, symbol) in line.chars().enumerate() {
+            if self.opening(symbol).is_some() {
+                stack.push(symbol);
+            } else if self.closing(symbol).is_some() {
+                let expected = stack.pop().and_then(|open| self.matching_close(open));
 
-    for c in chars.iter().rev() {
-        match c {
-            '(' => completion.push_str(")"),
-            '[' => completion.push_str("]"),
-            '{' => completion.push_str("}"),
-            '<' => completion.push_str(">"),
-            _ => panic!("Unknown completion string for {}", c),
+                if expected != Some(symbol) {
+                    return AnalyzedLine::Corrupted(Corruption {
+                        column: i + 1,
+                        found: symbol,
+                        expected,
+                    });
+                }
+            }
+        }
+
+        if stack.is_empty() {
+            AnalyzedLine::Complete
+        } else {
+            AnalyzedLine::Incomplete(stack)
         }
     }
 
-    completion
-}
+    // closers for every open chunk, innermost first
+    pub fn completion_string(&self, stack: &[char]) -> String {
+        stack
+            .iter()
+            .rev()
+            .filter_map(|&open| self.matching_close(open))
+            .collect()
+    }
 
-fn score_completion(completion: String) -> usize {
-    completion.chars().fold(0, |acc, c| match c {
-        ')' => acc * 5 + 1,
-        ']' => acc * 5 + 2,
-        '}' => acc * 5 + 3,
-        '>' => acc * 5 + 4,
-        _ => panic!("Unknown completion string for {}", c),
-    })
-}
+    pub fn score_completion(&self, completion: &str) -> Option<usize> {
+        completion.chars().try_fold(0, |acc, c| {
+            Some(acc * self.completion_base + self.closing(c)?.completion_score)
+        })
+    }
 
-fn score_symbol(symbol: char) -> usize {
-    match symbol {
-        ')' => 3,
-        ']' => 57,
-        '}' => 1197,
-        '>' => 25137,
-        _ => panic!("No score for {}", symbol),
+    pub fn score_corruption(&self, symbol: char) -> Option<usize> {
+        self.closing(symbol).map(|pair| pair.corruption_score)
     }
-}
 
-fn is_closing_symbol(symbol: char) -> bool {
-    symbol == ')' || symbol == ']' || symbol == '}' || symbol == '>'
-}
+    fn matching_close(&self, open: char) -> Option<char> {
+        self.opening(open).map(|pair| pair.close)
+    }
+
+    fn opening(&self, symbol: char) -> Option<&BracketPair> {
+        self.pairs.iter().find(|pair| pair.open == symbol)
+    }
 
-fn matching_closing_symbol(symbol: char) -> char {
-    match symbol {
-        '(' => ')',
-        '[' => ']',
-        '{' => '}',
-        '<' => '>',
-        _ => panic!("No matching closing symbol for {}", symbol),
+    fn closing(&self, symbol: char) -> Option<&BracketPair> {
+        self.pairs.iter().find(|pair| pair.close == symbol)
     }
 }
 
@@ -134,35 +180,98 @@ mod tests {
 
     #[test]
     fn analysis() {
+        let checker = Checker::navigation();
+
         assert_eq!(
-            analyze(&String::from("{([(<{}[<>[]}>{[]{[(<()>")),
-            AnalyzedLine::Corrupted('}')
+            checker.analyze("{([(<{}[<>[]}>{[]{[(<()>"),
+            AnalyzedLine::Corrupted(Corruption {
+                column: 13,
+                found: '}',
+                expected: Some(']'),
+            })
         );
         assert_eq!(
-            analyze(&String::from("[({(<(())[]>[[{[]{<()<>>")),
+            checker.analyze("[({(<(())[]>[[{[]{<()<>>"),
             AnalyzedLine::Incomplete(vec!['[', '(', '{', '(', '[', '[', '{', '{'])
         );
+        assert_eq!(
+            checker.analyze("[<>({}){}[([])<>]]"),
+            AnalyzedLine::Complete
+        );
+        assert_eq!(
+            checker.analyze("()>"),
+            AnalyzedLine::Corrupted(Corruption {
+                column: 3,
+                found: '>',
+                expected: None,
+            })
+        );
     }
 
     #[test]
     fn completion() {
-        assert_eq!(completion_string(vec!['(', '[', '{', '<']), ">}])");
+        let checker = Checker::navigation();
+
+        assert_eq!(checker.completion_string(&['(', '[', '{', '<']), ">}])");
     }
 
     #[test]
     fn completion_score() {
-        assert_eq!(score_completion(String::from("}}]])})]")), 288957);
-        assert_eq!(score_completion(String::from(")}>]})")), 5566);
-        assert_eq!(score_completion(String::from("}}>}>))))")), 1480781);
-        assert_eq!(score_completion(String::from("]]}}]}]}>")), 995444);
-        assert_eq!(score_completion(String::from("])}>")), 294);
+        let checker = Checker::navigation();
+
+        assert_eq!(checker.score_completion("}}]])})]"), Some(288957));
+        assert_eq!(checker.score_completion(")}>]})"), Some(5566));
+        assert_eq!(checker.score_completion("}}>}>))))"), Some(1480781));
+        assert_eq!(checker.score_completion("]]}}]}]}>"), Some(995444));
+        assert_eq!(checker.score_completion("])}>"), Some(294));
+        assert_eq!(checker.score_completion("])x"), None);
     }
 
     #[test]
     fn symbol_score() {
-        assert_eq!(score_symbol(')'), 3);
-        assert_eq!(score_symbol(']'), 57);
-        assert_eq!(score_symbol('}'), 1197);
-        assert_eq!(score_symbol('>'), 25137);
+        let checker = Checker::navigation();
+
+        assert_eq!(checker.score_corruption(')'), Some(3));
+        assert_eq!(checker.score_corruption(']'), Some(57));
+        assert_eq!(checker.score_corruption('}'), Some(1197));
+        assert_eq!(checker.score_corruption('>'), Some(25137));
+        assert_eq!(checker.score_corruption('('), None);
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day10.txt");
+        let input = input_generator(input_text.trim());
+
+        assert_eq!(part1(&input), 387363);
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day10.txt");
+        let input = input_generator(input_text.trim());
+
+        assert_eq!(part2(&input), Some(4330777059));
+    }
+
+    #[test]
+    fn other_format_test() {
+        // guillemets and angle brackets with text in between, scored in base 3
+        let checker = Checker::new(&[('«', '»', 10, 1), ('⟨', '⟩', 20, 2)], 3);
+
+        assert_eq!(checker.analyze("«a ⟨b⟩ c»"), AnalyzedLine::Complete);
+        assert_eq!(
+            checker.analyze("«a ⟨b» c"),
+            AnalyzedLine::Corrupted(Corruption {
+                column: 6,
+                found: '»',
+                expected: Some('⟩'),
+            })
+        );
+
+        let stack = checker.analyze("«x ⟨y ⟨").incomplete().unwrap();
+
+        assert_eq!(checker.completion_string(&stack), "⟩⟩»");
+        assert_eq!(checker.score_completion("⟩⟩»"), Some((2 * 3 + 2) * 3 + 1));
     }
 }
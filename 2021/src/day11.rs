@@ -1,11 +1,14 @@
-const X: i8 = 10;
-const Y: i8 = 10;
+use animation::Frame;
+use std::error::Error;
+use std::fmt;
 
-type OctopusMap = [[u8; X as usize]; Y as usize];
-type Point = (i8, i8);
+// an octopus flashes once its energy goes past this
+const FLASH_THRESHOLD: u8 = 9;
+
+type Point = (usize, usize); // x, y
 
 // (x, y)
-const OFFSETS: [Point; 8] = [
+const OFFSETS: [(isize, isize); 8] = [
     (-1, -1),
     (0, -1),
     (1, -1),
@@ -16,106 +19,420 @@ const OFFSETS: [Point; 8] = [
     (1, 1),
 ];
 
-#[aoc_generator(day11)]
-fn input_generator(input: &str) -> OctopusMap {
-    let mut result = [[0; Y as usize]; X as usize];
+#[derive(Debug)]
+struct ParseError;
 
-    for (y, line) in input.split("\n").enumerate() {
-        for (x, n) in line.bytes().enumerate() {
-            result[y][x] = (n - 48) as u8;
+impl Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error parsing octopus energy levels")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GridError {
+    Empty,
+    SizeMismatch { expected: usize, found: usize }, // expected is width * height
+}
+
+impl Error for GridError {}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GridError::Empty => write!(f, "Error building grid, it has no octopuses"),
+            GridError::SizeMismatch { expected, found } => write!(
+                f,
+                "Error building grid, expected {} energy levels but found {}",
+                expected, found
+            ),
         }
     }
+}
 
-    result
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Flash {
+    pub cell: Point,
+    pub wave: usize, // 0 for octopuses that flashed on their own, n for ones set off by wave n - 1
 }
 
-#[aoc(day11, part1)]
-fn part1(map: &OctopusMap) -> usize {
-    let mut map = map.clone();
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepEvents {
+    pub step: usize, // 1-based
+    pub flashes: Vec<Flash>,
+    pub synchronized: bool, // every octopus flashed during this step
+}
 
-    (0..100).map(|_| step_map(&mut map)).sum()
+impl StepEvents {
+    pub fn waves(&self) -> usize {
+        self.flashes.last().map_or(0, |flash| flash.wave + 1)
+    }
 }
 
-#[aoc(day11, part2)]
-fn part2(map: &OctopusMap) -> usize {
-    let mut map = map.clone();
-    let mut iteration = 0;
+#[derive(Debug, Clone)]
+pub struct OctopusGrid {
+    width: usize,
+    height: usize,
+    energies: Vec<u8>, // row-major, `width * height` long
+    wrap: bool,        // edges wrap around, so the grid is a torus
+    step: usize,
+}
 
-    loop {
-        iteration += 1;
+impl OctopusGrid {
+    pub fn new(width: usize, height: usize, energies: Vec<u8>) -> Result<Self, GridError> {
+        if width == 0 || height == 0 {
+            return Err(GridError::Empty);
+        }
 
-        if step_map(&mut map) == (X * Y) as usize {
-            return iteration;
+        if energies.len() != width * height {
+            return Err(GridError::SizeMismatch {
+                expected: width * height,
+                found: energies.len(),
+            });
         }
+
+        Ok(OctopusGrid {
+            width,
+            height,
+            energies,
+            wrap: false,
+            step: 0,
+        })
+    }
+
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
     }
-}
 
-fn step_map(map: &mut OctopusMap) -> usize {
-    let mut flashed = [[0; Y as usize]; X as usize];
+    pub fn width(&self) -> usize {
+        self.width
+    }
 
-    for point in points_iter() {
-        step_octopus(&point, map, &mut flashed);
+    pub fn height(&self) -> usize {
+        self.height
     }
 
-    flashed
-        .iter()
-        .flat_map(|r| r.iter().filter(|&v| *v == 1))
-        .count()
-}
+    pub fn energy(&self, (x, y): Point) -> u8 {
+        self.energies[y * self.width + x]
+    }
+
+    /**
+     * Advances every octopus by one step. Flashes spread a wave at a time
+     * from a work list rather than by recursion, so cascades across big grids
+     * don't grow the call stack. Within a wave, flashes are in the order the
+     * octopuses crossed the threshold.
+     */
+    pub fn step(&mut self) -> StepEvents {
+        let mut flashed = vec![false; self.energies.len()];
+        let mut wave: Vec<usize> = Vec::new();
+        let mut flashes = Vec::new();
+
+        for (i, energy) in self.energies.iter_mut().enumerate() {
+            *energy += 1;
 
-fn step_octopus(point: &Point, map: &mut OctopusMap, flashed: &mut OctopusMap) {
-    let (x, y) = point;
+            if *energy > FLASH_THRESHOLD {
+                flashed[i] = true;
+                wave.push(i);
+            }
+        }
+
+        let mut n = 0;
+
+        while !wave.is_empty() {
+            let mut next = Vec::new();
+
+            for &i in &wave {
+                flashes.push(Flash {
+                    cell: (i % self.width, i / self.width),
+                    wave: n,
+                });
+
+                for j in self.neighbors(i) {
+                    if flashed[j] {
+                        continue;
+                    }
+
+                    self.energies[j] += 1;
+
+                    if self.energies[j] > FLASH_THRESHOLD {
+                        flashed[j] = true;
+                        next.push(j);
+                    }
+                }
+            }
 
-    // skip this octopus if it already flashed this step
-    if flashed[*y as usize][*x as usize] == 1 {
-        return;
+            wave = next;
+            n += 1;
+        }
+
+        for &Flash { cell: (x, y), .. } in &flashes {
+            self.energies[y * self.width + x] = 0;
+        }
+
+        self.step += 1;
+
+        StepEvents {
+            step: self.step,
+            synchronized: flashes.len() == self.energies.len(),
+            flashes,
+        }
     }
 
-    // increment
-    map[*y as usize][*x as usize] += 1;
+    // octopuses that just flashed are brightest, the rest glow brighter as they charge up
+    pub fn frame(&self) -> Frame {
+        let levels = self
+            .energies
+            .iter()
+            .map(|&energy| match energy {
+                0 => 255,
+                e => 40 + 20 * e.min(FLASH_THRESHOLD),
+            })
+            .collect();
+
+        // the grid always has `width * height` octopuses, so this can't fail
+        Frame {
+            width: self.width,
+            height: self.height,
+            levels,
+        }
+    }
+
+    // an endless stream of steps, take as many as needed
+    pub fn steps(&mut self) -> impl Iterator<Item = StepEvents> + '_ {
+        std::iter::from_fn(move || Some(self.step()))
+    }
+
+    /**
+     * Like `steps`, but stops once the grid is back in an energy layout it has
+     * already been in. Steps are deterministic, so by then every step the grid
+     * will ever take has been seen. Repeats are found with Brent's algorithm,
+     * which only keeps one earlier layout around at a time.
+     */
+    pub fn steps_until_cycle(&mut self) -> impl Iterator<Item = StepEvents> + '_ {
+        let mut saved = self.energies.clone();
+        let mut power = 1;
+        let mut length = 0;
+        let mut repeated = false;
+
+        std::iter::from_fn(move || {
+            if repeated {
+                return None;
+            }
+
+            let events = self.step();
+
+            length += 1;
+
+            if self.energies == saved {
+                repeated = true;
+            } else if length == power {
+                saved.clone_from(&self.energies);
+                power *= 2;
+                length = 0;
+            }
+
+            Some(events)
+        })
+    }
 
-    // handle flash
-    if map[*y as usize][*x as usize] == 10 {
-        // ensure this octopus doesn't flash twice in one step
-        flashed[*y as usize][*x as usize] = 1;
+    fn neighbors(&self, i: usize) -> Vec<usize> {
+        let (x, y) = ((i % self.width) as isize, (i / self.width) as isize);
+        let (w, h) = (self.width as isize, self.height as isize);
 
-        // for each neighboring octopus, step
-        for (nx, ny) in valid_neighbors(point) {
-            step_octopus(&(nx, ny), map, flashed);
+        let mut neighbors: Vec<usize> = OFFSETS
+            .iter()
+            .filter_map(|(dx, dy)| {
+                let (nx, ny) = if self.wrap {
+                    ((x + dx).rem_euclid(w), (y + dy).rem_euclid(h))
+                } else {
+                    (x + dx, y + dy)
+                };
+
+                (nx >= 0 && nx < w && ny >= 0 && ny < h).then(|| (ny * w + nx) as usize)
+            })
+            .filter(|&j| j != i)
+            .collect();
+
+        // on a torus narrower than 3 cells, several offsets land on the same octopus
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+}
+
+impl fmt::Display for OctopusGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.energies.chunks(self.width) {
+            for energy in row {
+                write!(f, "{}", energy)?;
+            }
+
+            writeln!(f)?;
         }
 
-        // since this ocotpus flashed, reset it's brightness to 0
-        map[*y as usize][*x as usize] = 0;
+        Ok(())
     }
 }
 
-fn points_iter() -> impl Iterator<Item = Point> {
-    (0..Y).flat_map(|y| (0..X).map(move |x| (x, y)))
+#[aoc_generator(day11)]
+pub fn input_generator(input: &str) -> Result<OctopusGrid, Box<dyn Error>> {
+    let width = input.lines().next().map_or(0, |line| line.len());
+    let mut energies = Vec::with_capacity(input.len());
+    let mut height = 0;
+
+    for line in input.lines() {
+        if line.len() != width {
+            return Err(Box::new(ParseError));
+        }
+
+        for c in line.chars() {
+            energies.push(c.to_digit(10).ok_or(ParseError)? as u8);
+        }
+
+        height += 1;
+    }
+
+    Ok(OctopusGrid::new(width, height, energies)?)
 }
 
-fn valid_neighbors(origin: &Point) -> Vec<Point> {
-    let (x, y) = origin;
+#[aoc(day11, part1)]
+pub fn part1(grid: &OctopusGrid) -> usize {
+    let mut grid = grid.clone();
 
-    OFFSETS
-        .iter()
-        .map(|(a, b)| (x + a, y + b))
-        .filter(|(x, y)| valid_point(*x, *y))
-        .collect()
+    grid.steps()
+        .take(100)
+        .map(|events| events.flashes.len())
+        .sum()
 }
 
-fn valid_point(x: i8, y: i8) -> bool {
-    x >= 0 && x < X && y >= 0 && y < Y
+#[aoc(day11, part2)]
+pub fn part2(grid: &OctopusGrid) -> Option<usize> {
+    let mut grid = grid.clone();
+    let synchronized = grid.steps_until_cycle().find(|events| events.synchronized);
+
+    synchronized.map(|events| events.step)
 }
 
-#[allow(unused)]
-fn debug(map: &OctopusMap) {
-    for line in map.iter() {
-        for n in line.iter() {
-            print!("{}", n);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "5483143223\n2745854711\n5264556173\n6141336146\n6357385478\n4167524645\n2176841721\n6882881134\n4846848554\n5283751526";
 
-        print!("\n");
+    #[test]
+    fn input() {
+        let grid = input_generator("123\n456").unwrap();
+
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+        assert_eq!(grid.energy((2, 1)), 6);
+        assert!(input_generator("123\n45").is_err());
+        assert!(input_generator("12x").is_err());
+        assert_eq!(
+            OctopusGrid::new(2, 2, vec![1, 2, 3]).unwrap_err(),
+            GridError::SizeMismatch {
+                expected: 4,
+                found: 3
+            }
+        );
+        assert!(input_generator("").is_err());
+        assert_eq!(
+            OctopusGrid::new(0, 3, vec![]).unwrap_err(),
+            GridError::Empty
+        );
+    }
+
+    #[test]
+    fn example_test() {
+        let grid = input_generator(EXAMPLE).unwrap();
+
+        assert_eq!(part1(&grid), 1656);
+        assert_eq!(part2(&grid), Some(195));
     }
 
-    println!("----------");
+    #[test]
+    fn waves_test() {
+        let mut grid = input_generator("11111\n19991\n19191\n19991\n11111").unwrap();
+        let events = grid.step();
+
+        assert_eq!(events.step, 1);
+        assert_eq!(events.flashes.len(), 9);
+        assert_eq!(events.waves(), 2);
+        assert!(events.flashes[..8].iter().all(|flash| flash.wave == 0));
+        assert_eq!(
+            events.flashes[8],
+            Flash {
+                cell: (2, 2),
+                wave: 1
+            }
+        );
+        assert!(!events.synchronized);
+        assert_eq!(grid.to_string(), "34543\n40004\n50005\n40004\n34543\n");
+    }
+
+    #[test]
+    fn never_synchronizes_test() {
+        let mut grid = input_generator("0\n2").unwrap();
+
+        assert_eq!(part2(&grid), None);
+        assert!(grid.steps_until_cycle().count() < 100);
+    }
+
+    #[test]
+    fn wrap_test() {
+        // a lone flash in the corner reaches the opposite corners on a torus
+        let mut grid = input_generator("9111\n1111\n1111\n1111")
+            .unwrap()
+            .with_wrap(true);
+
+        grid.step();
+
+        assert_eq!(grid.to_string(), "0323\n3323\n2222\n3323\n");
+
+        // every octopus on a 2x2 torus neighbours every other one
+        let mut grid = input_generator("90\n00").unwrap().with_wrap(true);
+
+        grid.step();
+
+        assert_eq!(grid.to_string(), "02\n22\n");
+    }
+
+    #[test]
+    fn frame_test() {
+        let mut grid = input_generator("19\n11").unwrap();
+
+        assert_eq!(grid.frame().levels, vec![60, 220, 60, 60]);
+
+        grid.step();
+
+        assert_eq!(grid.frame().levels, vec![100, 255, 100, 100]);
+    }
+
+    #[test]
+    fn large_grid_test() {
+        // one big cascade across the whole grid in a single step
+        let row = "9".repeat(500);
+        let mut grid = input_generator(&vec![row; 500].join("\n")).unwrap();
+        let events = grid.step();
+
+        assert!(events.synchronized);
+        assert_eq!(events.flashes.len(), 250_000);
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day11.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input), 1652);
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day11.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input), Some(220));
+    }
 }
@@ -1,17 +1,74 @@
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt;
 
 const OFFSETS: [Point; 4] = [(-1, 0), (0, -1), (1, 0), (0, 1)];
 
+// the puzzle tiles the map 5 times in each direction, wrapping risk values above 9 back to 1
+const EXPANSION_FACTOR: usize = 5;
+const RISK_MODULUS: usize = 9;
+
 type Point = (isize, isize);
 type Node = Vec<Edge>;
 
+#[derive(Debug)]
+struct ParseError;
+
+impl Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error parsing chiton risk map")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExpandError {
+    ZeroFactor,
+    ZeroModulus,
+}
+
+impl Error for ExpandError {}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpandError::ZeroFactor => write!(f, "Error expanding map, factor must be at least 1"),
+            ExpandError::ZeroModulus => {
+                write!(f, "Error expanding map, risk modulus must be at least 1")
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Edge {
     node: usize, // index in node list this edge points to
     cost: usize, // cost to move from the node that owns this edge to the node at `node`
 }
 
+#[derive(Debug)]
+pub struct RiskMap {
+    width: usize,
+    height: usize,
+    risks: Vec<Vec<usize>>,
+    nodes: Vec<Node>,
+}
+
+// the lowest risk path through a map, `path` runs from the top left to the bottom right (inclusive)
+#[derive(Debug, PartialEq)]
+pub struct Route {
+    pub cost: usize,
+    pub path: Vec<Point>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderMode {
+    Ascii, // route risk values are kept, everything else is blanked out with `.`
+    Ansi,  // every risk value is drawn, the route is highlighted
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 struct State {
     cost: usize,
@@ -34,95 +91,151 @@ impl PartialOrd for State {
     }
 }
 
+impl RiskMap {
+    pub fn new(risks: Vec<Vec<usize>>) -> Self {
+        let height = risks.len();
+        let width = risks.first().map_or(0, |row| row.len());
+        let nodes = generate_nodes(&risks);
+
+        RiskMap {
+            width,
+            height,
+            risks,
+            nodes,
+        }
+    }
+
+    /**
+     * Tiles the map `factor` times horizontally and vertically. Each tile step
+     * to the right or down increases the risk by 1, wrapping values above
+     * `modulus` back around to 1.
+     */
+    pub fn expanded(&self, factor: usize, modulus: usize) -> Result<RiskMap, ExpandError> {
+        if factor == 0 {
+            return Err(ExpandError::ZeroFactor);
+        }
+
+        if modulus == 0 {
+            return Err(ExpandError::ZeroModulus);
+        }
+
+        Ok(RiskMap::new(expand_points(&self.risks, factor, modulus)))
+    }
+
+    pub fn point_at(&self, index: usize) -> Point {
+        ((index % self.width) as isize, (index / self.width) as isize)
+    }
+
+    pub fn render(&self, route: &Route, mode: RenderMode) -> String {
+        let mut on_route = vec![vec![false; self.width]; self.height];
+
+        for &(x, y) in &route.path {
+            on_route[y as usize][x as usize] = true;
+        }
+
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+
+        for (y, row) in self.risks.iter().enumerate() {
+            for (x, risk) in row.iter().enumerate() {
+                match (mode, on_route[y][x]) {
+                    (RenderMode::Ascii, true) => out.push_str(&risk.to_string()),
+                    (RenderMode::Ascii, false) => out.push('.'),
+                    (RenderMode::Ansi, true) => out.push_str(&format!("\x1b[1;32m{}\x1b[0m", risk)),
+                    (RenderMode::Ansi, false) => out.push_str(&format!("\x1b[2m{}\x1b[0m", risk)),
+                }
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
 #[aoc_generator(day15, part1)]
-pub fn input_generator_part1(input: &str) -> Vec<Node> {
-    let points: Vec<Vec<usize>> = generate_points(input);
-    generate_nodes(points)
+pub fn input_generator_part1(input: &str) -> Result<RiskMap, Box<dyn Error>> {
+    let points: Vec<Vec<usize>> = generate_points(input)?;
+    Ok(RiskMap::new(points))
 }
 
 #[aoc_generator(day15, part2)]
-pub fn input_generator_part2(input: &str) -> Vec<Node> {
-    let mut points: Vec<Vec<usize>> = generate_points(input);
-    expand_points(&mut points);
-    generate_nodes(points)
+pub fn input_generator_part2(input: &str) -> Result<RiskMap, Box<dyn Error>> {
+    let map = input_generator_part1(input)?;
+    Ok(map.expanded(EXPANSION_FACTOR, RISK_MODULUS)?)
 }
 
 // string -> 2D Vec of integer (risk values)
-fn generate_points(input: &str) -> Vec<Vec<usize>> {
-    input
-        .split("\n")
-        .map(|row| row.chars().map(|n| n as usize - '0' as usize).collect())
-        .collect()
+fn generate_points(input: &str) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
+    let points = input
+        .lines()
+        .map(|row| {
+            row.chars()
+                .map(|n| n.to_digit(10).map(|d| d as usize).ok_or(ParseError))
+                .collect::<Result<Vec<usize>, ParseError>>()
+        })
+        .collect::<Result<Vec<_>, ParseError>>()?;
+
+    // every row needs to be the same width or the node indices won't line up
+    if points.iter().any(|row| row.len() != points[0].len()) {
+        return Err(Box::new(ParseError));
+    }
+
+    Ok(points)
 }
 
 // 2D Vec of integers -> Vec of Node
-fn generate_nodes(points: Vec<Vec<usize>>) -> Vec<Node> {
+fn generate_nodes(points: &[Vec<usize>]) -> Vec<Node> {
     points
         .iter()
         .enumerate()
         .flat_map(|(y, row)| {
-            let points = points.clone();
-
-            (0..row.len()).map(move |x| find_edges(&points, (x as isize, y as isize)))
+            (0..row.len()).map(move |x| find_edges(points, (x as isize, y as isize)))
         })
         .collect()
 }
 
-// 🤷🏻‍♂️ tile the points 5 times horizontally and vertically, this is gross
-fn expand_points(points: &mut Vec<Vec<usize>>) {
-    let original_points = points.clone();
-    let height = original_points.len();
-    let width = original_points[0].len();
-
-    // expand horizontally
-    for dx in 0..4 {
-        for y in 0..height {
-            for x in 0..width {
-                let value = points[y][x + width * dx];
-                points[y].push(value % 9 + 1);
-            }
-        }
-    }
+// see `RiskMap::expanded`, `factor` and `modulus` are known to be non-zero
+fn expand_points(points: &[Vec<usize>], factor: usize, modulus: usize) -> Vec<Vec<usize>> {
+    let height = points.len();
+    let width = points.first().map_or(0, |row| row.len());
 
-    // expand vertically
-    for dy in 1..5 {
-        for y in 0..height {
-            points.push(Vec::with_capacity(width * 5));
+    (0..height * factor)
+        .map(|y| {
+            (0..width * factor)
+                .map(|x| {
+                    let value = points[y % height][x % width];
+                    let steps = x / width + y / height;
 
-            for x in 0..width * 5 {
-                let new_y = y + height * dy;
-                let value = points[new_y - height][x];
-                points[new_y].push(value % 9 + 1);
-            }
-        }
-    }
+                    if steps == 0 {
+                        value
+                    } else {
+                        (value + steps - 1) % modulus + 1
+                    }
+                })
+                .collect()
+        })
+        .collect()
 }
 
 #[aoc(day15, part1)]
-pub fn part1(graph: &Vec<Node>) -> usize {
-    if let Some(distance) = shortest_path(&graph) {
-        distance
-    } else {
-        panic!("Could not find a shortest path")
-    }
+pub fn part1(map: &RiskMap) -> Option<usize> {
+    shortest_path(map).map(|route| route.cost)
 }
 
 #[aoc(day15, part2)]
-pub fn part2(graph: &Vec<Node>) -> usize {
-    if let Some(distance) = shortest_path(&graph) {
-        distance
-    } else {
-        panic!("Could not find a shortest path")
-    }
+pub fn part2(map: &RiskMap) -> Option<usize> {
+    shortest_path(map).map(|route| route.cost)
 }
 
-fn find_edges(points: &Vec<Vec<usize>>, origin: Point) -> Vec<Edge> {
-    valid_neighbors(&origin, (points[0].len() as isize, points.len() as isize))
+fn find_edges(points: &[Vec<usize>], origin: Point) -> Vec<Edge> {
+    let width = points[0].len();
+
+    valid_neighbors(&origin, (width as isize, points.len() as isize))
         .iter()
         .map(|&(x, y)| {
             Edge {
                 // dest node position in master list
-                node: x as usize + y as usize * points.len(),
+                node: x as usize + y as usize * width,
                 cost: points[y as usize][x as usize],
             }
         })
@@ -130,10 +243,17 @@ fn find_edges(points: &Vec<Vec<usize>>, origin: Point) -> Vec<Edge> {
 }
 
 // Djikstra's Algorithm https://en.wikipedia.org/wiki/Dijkstra%27s_algorithm
-fn shortest_path(graph: &Vec<Node>) -> Option<usize> {
+pub fn shortest_path(map: &RiskMap) -> Option<Route> {
+    let graph = &map.nodes;
+
+    if graph.is_empty() {
+        return None;
+    }
+
     let start = 0;
     let end = graph.len() - 1;
     let mut dist: Vec<_> = (0..graph.len()).map(|_| usize::MAX).collect();
+    let mut prev: Vec<Option<usize>> = vec![None; graph.len()];
     let mut heap = BinaryHeap::new();
 
     // start at the beginning, it's free
@@ -147,7 +267,10 @@ fn shortest_path(graph: &Vec<Node>) -> Option<usize> {
     // iterate over the lowest cost next node
     while let Some(State { cost, position }) = heap.pop() {
         if position == end {
-            return Some(cost);
+            return Some(Route {
+                cost,
+                path: build_path(map, &prev, end),
+            });
         }
 
         // there's a better way
@@ -168,6 +291,7 @@ fn shortest_path(graph: &Vec<Node>) -> Option<usize> {
 
                 // relaxation, we found a better way
                 dist[next.position] = next.cost;
+                prev[next.position] = Some(position);
             }
         }
     }
@@ -175,6 +299,20 @@ fn shortest_path(graph: &Vec<Node>) -> Option<usize> {
     None
 }
 
+// walk the chain of predecessors back from the end to the start
+fn build_path(map: &RiskMap, prev: &[Option<usize>], end: usize) -> Vec<Point> {
+    let mut path = vec![map.point_at(end)];
+    let mut current = end;
+
+    while let Some(previous) = prev[current] {
+        path.push(map.point_at(previous));
+        current = previous;
+    }
+
+    path.reverse();
+    path
+}
+
 fn valid_neighbors(point: &Point, dimensions: (isize, isize)) -> Vec<Point> {
     OFFSETS
         .iter()
@@ -185,3 +323,87 @@ fn valid_neighbors(point: &Point, dimensions: (isize, isize)) -> Vec<Point> {
 fn valid_point(x: isize, y: isize, width: isize, height: isize) -> bool {
     x >= 0 && x < width && y >= 0 && y < height
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "1163751742\n1381373672\n2136511328\n3694931569\n7463417111\n1319128137\n1359912421\n3125421639\n1293138521\n2311944581";
+
+    #[test]
+    fn example_test() {
+        assert_eq!(part1(&input_generator_part1(EXAMPLE).unwrap()), Some(40));
+        assert_eq!(part2(&input_generator_part2(EXAMPLE).unwrap()), Some(315));
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day15.txt");
+        let input = input_generator_part1(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input), Some(602));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day15.txt");
+        let input = input_generator_part2(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input), Some(2935));
+    }
+
+    #[test]
+    fn non_square_route_test() {
+        let map = RiskMap::new(vec![vec![1, 9, 9], vec![1, 1, 1]])
+            .expanded(2, 3)
+            .unwrap();
+        let route = shortest_path(&map).unwrap();
+
+        assert_eq!(map.width, 6);
+        assert_eq!(map.height, 4);
+        assert_eq!(route.path.first(), Some(&(0, 0)));
+        assert_eq!(route.path.last(), Some(&(5, 3)));
+        assert_eq!(
+            route.cost,
+            route.path[1..]
+                .iter()
+                .map(|&(x, y)| map.risks[y as usize][x as usize])
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn expanded_test() {
+        let map = input_generator_part1("35\n14").unwrap();
+
+        assert_eq!(
+            map.expanded(3, 5).unwrap().risks,
+            vec![
+                vec![3, 5, 4, 1, 5, 2],
+                vec![1, 4, 2, 5, 3, 1],
+                vec![4, 1, 5, 2, 1, 3],
+                vec![2, 5, 3, 1, 4, 2],
+                vec![5, 2, 1, 3, 2, 4],
+                vec![3, 1, 4, 2, 5, 3],
+            ]
+        );
+        assert_eq!(map.expanded(1, 9).unwrap().risks, map.risks);
+        assert_eq!(map.expanded(2, 0).unwrap_err(), ExpandError::ZeroModulus);
+        assert_eq!(map.expanded(0, 9).unwrap_err(), ExpandError::ZeroFactor);
+    }
+
+    #[test]
+    fn render_test() {
+        let map = input_generator_part1("19\n11").unwrap();
+        let route = shortest_path(&map).unwrap();
+
+        assert_eq!(route.path, vec![(0, 0), (0, 1), (1, 1)]);
+        assert_eq!(map.render(&route, RenderMode::Ascii), "1.\n11\n");
+    }
+
+    #[test]
+    fn empty_map_test() {
+        assert_eq!(part1(&input_generator_part1("").unwrap()), None);
+        assert!(input_generator_part1("12\n3").is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::fmt;
+use std::ops::RangeInclusive;
 
 #[derive(Debug)]
 struct ParseError;
@@ -12,7 +13,45 @@ impl fmt::Display for ParseError {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum TargetError {
+    EmptyXRange, // x_min is greater than x_max
+    EmptyYRange, // y_min is greater than y_max
+}
+
+impl Error for TargetError {}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TargetError::EmptyXRange => write!(f, "Error creating target, x_min is above x_max"),
+            TargetError::EmptyYRange => write!(f, "Error creating target, y_min is above y_max"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SolveError {
+    Unbounded,
+}
+
+impl Error for SolveError {}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::Unbounded => write!(
+                f,
+                "Target contains a resting point of the probe, infinitely many velocities hit it"
+            ),
+        }
+    }
+}
+
+const SVG_SCALE: isize = 10; // pixels per grid cell
+
 type Position = (isize, isize); // x, y
+type Velocity = (isize, isize); // vx, vy
 
 #[derive(Debug)]
 pub struct Target {
@@ -22,6 +61,18 @@ pub struct Target {
     y_max: isize,
 }
 
+#[derive(Debug)]
+pub struct Solution {
+    pub max_height: Option<isize>, // highest point reached by any hitting trajectory
+    pub velocities: Vec<Velocity>, // every launch velocity that hits the target, sorted
+}
+
+#[derive(Debug)]
+pub struct Trajectory {
+    pub positions: Vec<Position>, // every position the probe occupied, starting at the origin
+    pub hit: bool,
+}
+
 #[derive(Debug)]
 pub struct Probe {
     position: Position,
@@ -29,8 +80,62 @@ pub struct Probe {
     vy: isize,
 }
 
+impl Target {
+    pub fn new(
+        x_min: isize,
+        x_max: isize,
+        y_min: isize,
+        y_max: isize,
+    ) -> Result<Target, TargetError> {
+        if x_min > x_max {
+            return Err(TargetError::EmptyXRange);
+        }
+
+        if y_min > y_max {
+            return Err(TargetError::EmptyYRange);
+        }
+
+        Ok(Target {
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+        })
+    }
+}
+
+impl Trajectory {
+    // highest point of the trajectory, the first one reached if it levels off for a step
+    pub fn apex(&self) -> Position {
+        self.positions.iter().fold(
+            self.positions[0],
+            |apex, &p| if p.1 > apex.1 { p } else { apex },
+        )
+    }
+
+    // where the probe hit the target, or the point at which it could no longer reach it
+    pub fn end(&self) -> Position {
+        self.positions[self.positions.len() - 1]
+    }
+
+    // (x_min, x_max, y_min, y_max) covering the trajectory and the target
+    fn bounds(&self, target: &Target) -> (isize, isize, isize, isize) {
+        self.positions.iter().fold(
+            (
+                target.x_min.min(0),
+                target.x_max.max(0),
+                target.y_min.min(0),
+                target.y_max.max(0),
+            ),
+            |(x_min, x_max, y_min, y_max), &(x, y)| {
+                (x_min.min(x), x_max.max(x), y_min.min(y), y_max.max(y))
+            },
+        )
+    }
+}
+
 #[aoc_generator(day17)]
-fn input_generator(input: &str) -> Result<Target, Box<dyn Error>> {
+pub fn input_generator(input: &str) -> Result<Target, Box<dyn Error>> {
     let (x_range, y_range) = input
         .trim_start_matches("target area: ")
         .split_once(", ")
@@ -44,66 +149,382 @@ fn input_generator(input: &str) -> Result<Target, Box<dyn Error>> {
         .split_once("..")
         .ok_or(ParseError)?;
 
-    Ok(Target {
-        x_min: x_min.parse()?,
-        x_max: x_max.parse()?,
-        y_min: y_min.parse()?,
-        y_max: y_max.parse()?,
-    })
+    Ok(Target::new(
+        x_min.parse()?,
+        x_max.parse()?,
+        y_min.parse()?,
+        y_max.parse()?,
+    )?)
 }
 
 #[aoc(day17, part1)]
-fn part1(target: &Target) -> isize {
-    build_trajectories(&target)
-        .max()
-        .expect("Could not find a maximum!")
+fn part1(target: &Target) -> Result<isize, Box<dyn Error>> {
+    solve(target)?
+        .max_height
+        .ok_or_else(|| "No trajectory hits the target".into())
 }
 
 #[aoc(day17, part2)]
-fn part2(target: &Target) -> usize {
-    build_trajectories(&target).count()
+fn part2(target: &Target) -> Result<usize, Box<dyn Error>> {
+    Ok(solve(target)?.velocities.len())
 }
 
-fn build_trajectories(target: &Target) -> impl Iterator<Item = isize> + '_ {
-    (0..=target.x_max)
-        .flat_map(move |vx| {
-            (target.y_min..target.y_min.abs()).map(move |vy| calculate_trajectory(vx, vy, &target))
-        })
-        .filter_map(|(hit, max_y)| if hit { Some(max_y) } else { None })
+pub fn solve(target: &Target) -> Result<Solution, SolveError> {
+    let vx_range = vx_bounds(target);
+
+    // if the probe can come to rest horizontally inside the target while the target spans y=0, every
+    // upward launch falls back through y=0 at that x eventually, no matter how high it goes
+    if target.y_min <= 0
+        && target.y_max >= 0
+        && vx_range
+            .clone()
+            .any(|vx| (target.x_min..=target.x_max).contains(&resting_x(vx)))
+    {
+        return Err(SolveError::Unbounded);
+    }
+
+    let max_speed_x = vx_range.start().abs().max(vx_range.end().abs());
+    let mut max_height = None;
+    let mut velocities = Vec::new();
+
+    for vx in vx_range {
+        for vy in vy_bounds(target, max_speed_x) {
+            let trajectory = calculate_trajectory(vx, vy, target);
+
+            if trajectory.hit {
+                max_height = max_height.max(Some(trajectory.apex().1));
+                velocities.push((vx, vy));
+            }
+        }
+    }
+
+    Ok(Solution {
+        max_height,
+        velocities,
+    })
+}
+
+// triangular number, the distance covered by a launch speed of `n` before drag (or gravity) stops it
+fn triangle(n: isize) -> isize {
+    n * (n + 1) / 2
+}
+
+// smallest launch speed that covers at least `distance` before drag brings it to a halt
+fn min_speed(distance: isize) -> isize {
+    let mut speed = (((8 * distance + 1) as f64).sqrt() as isize - 1) / 2;
+
+    while triangle(speed) < distance {
+        speed += 1;
+    }
+
+    speed
+}
+
+// where the probe stops moving horizontally for a given launch vx
+fn resting_x(vx: isize) -> isize {
+    vx.signum() * triangle(vx.abs())
+}
+
+// any faster and the first step overshoots the far edge, any slower and drag stops the probe
+// short of the near edge
+fn vx_bounds(target: &Target) -> RangeInclusive<isize> {
+    let lo = if target.x_min > 0 {
+        min_speed(target.x_min)
+    } else {
+        target.x_min
+    };
+
+    let hi = if target.x_max < 0 {
+        -min_speed(-target.x_max)
+    } else {
+        target.x_max
+    };
+
+    lo..=hi
+}
+
+// the trajectory is symmetric around its apex, so a probe launched upward at vy comes back through
+// y=0 moving at -(vy + 1); launching faster than that skips right past a target below the origin
+fn vy_bounds(target: &Target, max_speed_x: isize) -> RangeInclusive<isize> {
+    let lo = if target.y_min > 0 {
+        min_speed(target.y_min)
+    } else {
+        target.y_min
+    };
+
+    let mut hi = target.y_max.max(-target.y_min - 1);
+
+    // a target spanning y=0 can still be hit on the way back down through y=0 while the probe is
+    // drifting horizontally, which takes at most `max_speed_x` steps
+    if target.y_min <= 0 && target.y_max >= 0 {
+        hi = hi.max(max_speed_x);
+    }
+
+    lo..=hi
 }
 
 fn step_probe(probe: &mut Probe) {
     probe.position.0 += probe.vx;
     probe.position.1 += probe.vy;
     probe.vy -= 1;
-
-    if probe.vx > 0 {
-        probe.vx += -1 * (probe.vx / probe.vx.abs());
-    }
+    probe.vx -= probe.vx.signum();
 }
 
-fn calculate_trajectory(vx: isize, vy: isize, target: &Target) -> (bool, isize) {
-    let mut max_y = 0;
+pub fn calculate_trajectory(vx: isize, vy: isize, target: &Target) -> Trajectory {
     let mut p = Probe {
         position: (0, 0),
         vx,
         vy,
     };
+    let mut positions = vec![p.position];
 
-    while !is_hit(p.position, &target) && !is_miss(p.position, &target) {
+    while !is_hit(p.position, target) && !is_miss(&p, target) {
         step_probe(&mut p);
-        max_y = max_y.max(p.position.1);
+        positions.push(p.position);
     }
 
-    (is_hit(p.position, &target), max_y)
+    Trajectory {
+        positions,
+        hit: is_hit(p.position, target),
+    }
 }
 
 fn is_hit(position: Position, target: &Target) -> bool {
     let (x, y) = position;
-    x >= target.x_min && x <= target.x_max && y >= target.y_min && y <= target.y_max
+    (target.x_min..=target.x_max).contains(&x) && (target.y_min..=target.y_max).contains(&y)
 }
 
-fn is_miss(position: Position, target: &Target) -> bool {
-    let (x, y) = position;
-    x > target.x_max || y < target.y_min
+// the probe can never come back once it is below the target and falling, or past the target
+// horizontally and not moving back towards it
+fn is_miss(probe: &Probe, target: &Target) -> bool {
+    let (x, y) = probe.position;
+
+    (y < target.y_min && probe.vy < 0)
+        || (x > target.x_max && probe.vx >= 0)
+        || (x < target.x_min && probe.vx <= 0)
+}
+
+// draws the trajectory like the puzzle illustrations, with a few extra markers:
+//   S = launch point, # = probe position, T = target area,
+//   ^ = apex, X = where the probe hit the target, ! = where it missed
+pub fn render_ascii(target: &Target, trajectory: &Trajectory) -> String {
+    let (x_min, x_max, y_min, y_max) = trajectory.bounds(target);
+    let width = (x_max - x_min + 1) as usize;
+    let height = (y_max - y_min + 1) as usize;
+    let mut grid = vec![vec!['.'; width]; height];
+    let mut plot = |(x, y): Position, c: char| {
+        grid[(y_max - y) as usize][(x - x_min) as usize] = c;
+    };
+
+    for y in target.y_min..=target.y_max {
+        for x in target.x_min..=target.x_max {
+            plot((x, y), 'T');
+        }
+    }
+
+    for &position in &trajectory.positions {
+        plot(position, '#');
+    }
+
+    plot(trajectory.apex(), '^');
+    plot(trajectory.end(), if trajectory.hit { 'X' } else { '!' });
+    plot((0, 0), 'S');
+
+    grid.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// same picture as `render_ascii` as an SVG, one unit per cell scaled up by `SVG_SCALE`
+pub fn render_svg(target: &Target, trajectory: &Trajectory) -> String {
+    let (x_min, x_max, y_min, y_max) = trajectory.bounds(target);
+    let width = (x_max - x_min + 1) * SVG_SCALE;
+    let height = (y_max - y_min + 1) * SVG_SCALE;
+
+    // svg y grows downwards, flip it and shift everything to the center of its cell
+    let to_svg = |(x, y): Position| {
+        (
+            (x - x_min) * SVG_SCALE + SVG_SCALE / 2,
+            (y_max - y) * SVG_SCALE + SVG_SCALE / 2,
+        )
+    };
+    let circle = |position: Position, r: isize, fill: &str| {
+        let (cx, cy) = to_svg(position);
+        format!(
+            r#"  <circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+            cx, cy, r, fill
+        )
+    };
+
+    let mut svg = vec![
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" width="{}" height="{}">"#,
+            width, height, width, height
+        ),
+        format!(
+            r##"  <rect x="{}" y="{}" width="{}" height="{}" fill="#8fbcbb" fill-opacity="0.5"/>"##,
+            (target.x_min - x_min) * SVG_SCALE,
+            (y_max - target.y_max) * SVG_SCALE,
+            (target.x_max - target.x_min + 1) * SVG_SCALE,
+            (target.y_max - target.y_min + 1) * SVG_SCALE
+        ),
+        format!(
+            r##"  <polyline points="{}" fill="none" stroke="#4c566a"/>"##,
+            trajectory
+                .positions
+                .iter()
+                .map(|&p| {
+                    let (x, y) = to_svg(p);
+                    format!("{},{}", x, y)
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+    ];
+
+    for &position in &trajectory.positions {
+        svg.push(circle(position, SVG_SCALE / 5, "#4c566a"));
+    }
+
+    svg.push(circle(trajectory.apex(), SVG_SCALE / 3, "#ebcb8b"));
+    svg.push(circle(
+        trajectory.end(),
+        SVG_SCALE / 3,
+        if trajectory.hit { "#a3be8c" } else { "#bf616a" },
+    ));
+    svg.push(circle((0, 0), SVG_SCALE / 3, "#5e81ac"));
+    svg.push("</svg>".to_string());
+
+    svg.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(x_min: isize, x_max: isize, y_min: isize, y_max: isize) -> Target {
+        Target::new(x_min, x_max, y_min, y_max).unwrap()
+    }
+
+    #[test]
+    fn example_test() {
+        let input = input_generator("target area: x=20..30, y=-10..-5").unwrap();
+        let solution = solve(&input).unwrap();
+
+        assert_eq!(solution.max_height, Some(45));
+        assert_eq!(solution.velocities.len(), 112);
+        assert!(solution.velocities.contains(&(6, 9)));
+        assert!(solution.velocities.contains(&(30, -10)));
+    }
+
+    #[test]
+    fn new_target_test() {
+        let solution = solve(&Target::new(20, 30, -10, -5).unwrap()).unwrap();
+
+        assert_eq!(solution.max_height, Some(45));
+        assert_eq!(
+            Target::new(30, 20, -10, -5).unwrap_err(),
+            TargetError::EmptyXRange
+        );
+        assert_eq!(
+            Target::new(20, 30, -5, -10).unwrap_err(),
+            TargetError::EmptyYRange
+        );
+        assert!(input_generator("target area: x=30..20, y=-10..-5").is_err());
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day17.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input).unwrap(), 3570);
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day17.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input).unwrap(), 1919);
+    }
+
+    #[test]
+    fn mirrored_target_test() {
+        let right = solve(&target(20, 30, -10, -5)).unwrap();
+        let left = solve(&target(-30, -20, -10, -5)).unwrap();
+        let mut mirrored: Vec<Velocity> =
+            right.velocities.iter().map(|&(vx, vy)| (-vx, vy)).collect();
+
+        mirrored.sort();
+
+        assert_eq!(left.max_height, right.max_height);
+        assert_eq!(left.velocities, mirrored);
+    }
+
+    #[test]
+    fn other_quadrants_test() {
+        // above the origin the apex is the top of the target at best
+        let above = solve(&target(5, 10, 3, 6)).unwrap();
+        assert!(above.velocities.contains(&(3, 3)));
+        assert!(above.max_height.unwrap() >= 6);
+
+        // straddling x=0 lets the probe drop straight down
+        let straddling = solve(&target(-2, 2, -10, -5)).unwrap();
+        assert!(straddling.velocities.contains(&(0, 9)));
+        assert_eq!(straddling.max_height, Some(45));
+    }
+
+    #[test]
+    fn render_ascii_test() {
+        let input = input_generator("target area: x=20..30, y=-10..-5").unwrap();
+        let trajectory = calculate_trajectory(7, 2, &input);
+        let expected = [
+            ".............^....#............",
+            ".......#..............#........",
+            "...............................",
+            "S........................#.....",
+            "...............................",
+            "...............................",
+            "...........................#...",
+            "...............................",
+            "....................TTTTTTTTTTT",
+            "....................TTTTTTTTTTT",
+            "....................TTTTTTTTXTT",
+            "....................TTTTTTTTTTT",
+            "....................TTTTTTTTTTT",
+            "....................TTTTTTTTTTT",
+        ];
+
+        assert!(trajectory.hit);
+        assert_eq!(trajectory.apex(), (13, 3));
+        assert_eq!(render_ascii(&input, &trajectory), expected.join("\n"));
+    }
+
+    #[test]
+    fn render_miss_test() {
+        let input = input_generator("target area: x=20..30, y=-10..-5").unwrap();
+        let trajectory = calculate_trajectory(17, -4, &input);
+        let ascii = render_ascii(&input, &trajectory);
+        let svg = render_svg(&input, &trajectory);
+
+        assert!(!trajectory.hit);
+        assert_eq!(trajectory.end(), (33, -9));
+        assert!(ascii.lines().nth(9).unwrap().ends_with('!'));
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert!(svg.contains("#bf616a"));
+    }
+
+    #[test]
+    fn unbounded_test() {
+        assert!(matches!(
+            solve(&target(-2, 2, -1, 1)),
+            Err(SolveError::Unbounded)
+        ));
+        assert!(matches!(
+            solve(&target(5, 7, -3, 3)),
+            Err(SolveError::Unbounded)
+        ));
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::num::ParseIntError;
@@ -26,68 +27,199 @@ impl From<ParseIntError> for ParseError {
     }
 }
 
+// a parse error along with the 1-based line of the script it happened on
 #[derive(Debug, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub error: ParseError,
+}
+
+impl Error for ScriptError {}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.error)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Cmd {
     Up(usize),
     Down(usize),
     Forward(usize),
+    Custom(String, usize), // only valid if the submarine running it has it registered
 }
 
 impl FromStr for Cmd {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Cmd, ParseError> {
-        let (dir, amount) = s.split_once(" ").ok_or(ParseError::InvalidCmd)?;
+        let (dir, amount) = s.split_once(' ').ok_or(ParseError::InvalidCmd)?;
         let amount = amount.parse()?;
 
         match dir {
             "up" => Ok(Cmd::Up(amount)),
             "down" => Ok(Cmd::Down(amount)),
             "forward" => Ok(Cmd::Forward(amount)),
-            _ => Err(ParseError::InvalidCmd),
+            "" => Err(ParseError::InvalidCmd),
+            _ => Ok(Cmd::Custom(dir.to_string(), amount)),
         }
     }
 }
 
-#[aoc_generator(day2)]
-pub fn input_generator(input: &str) -> Result<Vec<Cmd>, ParseError> {
-    input.split("\n").map(|n| n.parse()).collect()
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Position {
+    pub horizontal: i64,
+    pub depth: i64,
+    pub aim: i64,
 }
 
-#[aoc(day2, part1)]
-pub fn part1(commands: &[Cmd]) -> usize {
-    let mut h = 0;
-    let mut d = 0;
-
-    for c in commands {
-        match c {
-            Cmd::Up(x) => d -= x,
-            Cmd::Down(x) => d += x,
-            Cmd::Forward(x) => h += x,
-        }
+// what the three built in commands mean
+pub trait Interpreter {
+    fn up(&self, position: &mut Position, x: i64);
+    fn down(&self, position: &mut Position, x: i64);
+    fn forward(&self, position: &mut Position, x: i64);
+}
+
+// the first reading of the manual, up and down change depth directly
+pub struct Direct;
+
+impl Interpreter for Direct {
+    fn up(&self, position: &mut Position, x: i64) {
+        position.depth -= x;
+    }
+
+    fn down(&self, position: &mut Position, x: i64) {
+        position.depth += x;
     }
 
-    h * d
+    fn forward(&self, position: &mut Position, x: i64) {
+        position.horizontal += x;
+    }
 }
 
-#[aoc(day2, part2)]
-pub fn part2(commands: &[Cmd]) -> usize {
-    let mut h = 0;
-    let mut d = 0;
-    let mut aim = 0;
-
-    for c in commands {
-        match c {
-            Cmd::Up(x) => aim -= x,
-            Cmd::Down(x) => aim += x,
-            Cmd::Forward(x) => {
-                h += x;
-                d += aim * x;
+// the second reading, up and down steer and forward dives along the aim
+pub struct Aimed;
+
+impl Interpreter for Aimed {
+    fn up(&self, position: &mut Position, x: i64) {
+        position.aim -= x;
+    }
+
+    fn down(&self, position: &mut Position, x: i64) {
+        position.aim += x;
+    }
+
+    fn forward(&self, position: &mut Position, x: i64) {
+        position.horizontal += x;
+        position.depth += position.aim * x;
+    }
+}
+
+pub type Handler = fn(&mut Position, i64);
+
+pub struct Submarine<I: Interpreter> {
+    interpreter: I,
+    commands: HashMap<String, Handler>,
+}
+
+impl<I: Interpreter> Submarine<I> {
+    pub fn new(interpreter: I) -> Self {
+        Submarine {
+            interpreter,
+            commands: HashMap::new(),
+        }
+    }
+
+    // adds a command on top of the built in ones, which can't be overridden
+    pub fn register(mut self, name: &str, handler: Handler) -> Self {
+        self.commands.insert(name.to_string(), handler);
+        self
+    }
+
+    // parses a script, rejecting any command this submarine doesn't know
+    pub fn parse(&self, script: &str) -> Result<Vec<Cmd>, ScriptError> {
+        let commands = input_generator(script)?;
+
+        self.validate(&commands)?;
+
+        Ok(commands)
+    }
+
+    pub fn validate(&self, commands: &[Cmd]) -> Result<(), ScriptError> {
+        match commands.iter().position(|cmd| !self.knows(cmd)) {
+            Some(i) => Err(ScriptError {
+                line: i + 1,
+                error: ParseError::InvalidCmd,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // the position after each command, starting from the surface
+    pub fn trace(&self, commands: &[Cmd]) -> Result<Vec<Position>, ScriptError> {
+        self.validate(commands)?;
+
+        let mut position = Position::default();
+
+        Ok(commands
+            .iter()
+            .map(|cmd| {
+                self.execute(&mut position, cmd);
+                position
+            })
+            .collect())
+    }
+
+    pub fn run(&self, commands: &[Cmd]) -> Result<Position, ScriptError> {
+        Ok(self.trace(commands)?.pop().unwrap_or_default())
+    }
+
+    fn knows(&self, cmd: &Cmd) -> bool {
+        match cmd {
+            Cmd::Custom(name, _) => self.commands.contains_key(name),
+            _ => true,
+        }
+    }
+
+    fn execute(&self, position: &mut Position, cmd: &Cmd) {
+        match cmd {
+            Cmd::Up(x) => self.interpreter.up(position, *x as i64),
+            Cmd::Down(x) => self.interpreter.down(position, *x as i64),
+            Cmd::Forward(x) => self.interpreter.forward(position, *x as i64),
+            Cmd::Custom(name, x) => {
+                if let Some(handler) = self.commands.get(name) {
+                    handler(position, *x as i64)
+                }
             }
         }
     }
+}
+
+#[aoc_generator(day2)]
+pub fn input_generator(input: &str) -> Result<Vec<Cmd>, ScriptError> {
+    input
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse()
+                .map_err(|error| ScriptError { line: i + 1, error })
+        })
+        .collect()
+}
+
+#[aoc(day2, part1)]
+pub fn part1(commands: &[Cmd]) -> Result<i64, ScriptError> {
+    let position = Submarine::new(Direct).run(commands)?;
 
-    h * d
+    Ok(position.horizontal * position.depth)
+}
+
+#[aoc(day2, part2)]
+pub fn part2(commands: &[Cmd]) -> Result<i64, ScriptError> {
+    let position = Submarine::new(Aimed).run(commands)?;
+
+    Ok(position.horizontal * position.depth)
 }
 
 #[cfg(test)]
@@ -105,37 +237,61 @@ mod tests {
 
     #[test]
     fn foo_command_is_invalid() {
-        let input = input_generator("foo 3\ndown 5\nforward 1");
+        let input = input_generator("foo 3\ndown 5\nforward 1").unwrap();
 
-        assert_eq!(input, Err(ParseError::InvalidCmd));
+        assert_eq!(
+            Submarine::new(Direct).validate(&input),
+            Err(ScriptError {
+                line: 1,
+                error: ParseError::InvalidCmd
+            })
+        );
     }
 
     #[test]
     fn up_without_param_is_invalid() {
         let input = input_generator("up\ndown 5\nforward 1");
 
-        assert_eq!(input, Err(ParseError::InvalidCmd));
+        assert_eq!(
+            input,
+            Err(ScriptError {
+                line: 1,
+                error: ParseError::InvalidCmd
+            })
+        );
     }
 
     #[test]
     fn empty_input_is_invalid() {
         let input = input_generator("");
 
-        assert_eq!(input, Err(ParseError::InvalidCmd));
+        assert_eq!(
+            input,
+            Err(ScriptError {
+                line: 1,
+                error: ParseError::InvalidCmd
+            })
+        );
     }
 
     #[test]
     fn invalid_command_format() {
         let input = input_generator("");
 
-        assert_eq!(format!("{}", input.unwrap_err()), "Invalid command");
+        assert_eq!(format!("{}", input.unwrap_err()), "Line 1: Invalid command");
     }
 
     #[test]
     fn up_with_letter_is_parse_error() {
         let input = input_generator("up x\ndown 5\nforward 1");
 
-        assert!(matches!(input, Err(ParseError::Parse(_))));
+        assert!(matches!(
+            input,
+            Err(ScriptError {
+                line: 1,
+                error: ParseError::Parse(_)
+            })
+        ));
     }
 
     #[test]
@@ -144,7 +300,7 @@ mod tests {
 
         assert_eq!(
             format!("{}", input.unwrap_err()),
-            "Failed to parse command parameter: invalid digit found in string"
+            "Line 1: Failed to parse command parameter: invalid digit found in string"
         );
     }
 
@@ -159,7 +315,7 @@ mod tests {
             Cmd::Forward(2),
         ];
 
-        assert_eq!(part1(&commands), 150);
+        assert_eq!(part1(&commands), Ok(150));
     }
 
     #[test]
@@ -173,6 +329,81 @@ mod tests {
             Cmd::Forward(2),
         ];
 
-        assert_eq!(part2(&commands), 900);
+        assert_eq!(part2(&commands), Ok(900));
+    }
+
+    #[test]
+    fn line_numbers() {
+        let input = input_generator("up 3\ndown 5\nforward x");
+
+        assert!(matches!(input, Err(ScriptError { line: 3, .. })));
+        assert_eq!(
+            Submarine::new(Aimed).parse("up 3\nreverse 2\nforward 1"),
+            Err(ScriptError {
+                line: 2,
+                error: ParseError::InvalidCmd
+            })
+        );
+    }
+
+    #[test]
+    fn trace_test() {
+        let commands = input_generator("forward 5\ndown 5\nforward 8").unwrap();
+        let trace = Submarine::new(Aimed).trace(&commands).unwrap();
+
+        assert_eq!(
+            trace,
+            vec![
+                Position {
+                    horizontal: 5,
+                    depth: 0,
+                    aim: 0
+                },
+                Position {
+                    horizontal: 5,
+                    depth: 0,
+                    aim: 5
+                },
+                Position {
+                    horizontal: 13,
+                    depth: 40,
+                    aim: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn registered_command() {
+        let submarine = Submarine::new(Direct).register("reverse", |position, x| {
+            position.horizontal -= x;
+        });
+        let commands = submarine.parse("forward 10\nreverse 4\ndown 2").unwrap();
+
+        assert_eq!(commands[1], Cmd::Custom("reverse".to_string(), 4));
+        assert_eq!(
+            submarine.run(&commands),
+            Ok(Position {
+                horizontal: 6,
+                depth: 2,
+                aim: 0
+            })
+        );
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day2.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input), Ok(1670340));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day2.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input), Ok(1954293920));
     }
 }
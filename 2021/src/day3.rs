@@ -1,205 +1,294 @@
+use num_bigint::BigUint;
 use std::error::Error;
+use std::fmt;
 
-#[aoc_generator(day3)]
-pub fn input_generator(input: &str) -> Vec<String> {
-    input.split("\n").map(|s| s.to_string()).collect()
+#[derive(Debug, PartialEq)]
+pub enum ReportParseError {
+    InvalidBit(char),
+    MismatchedWidth(usize), // line number of the entry that isn't as wide as the first one
 }
 
-#[derive(Debug)]
-struct DiagnosticReport {
-    entries: Vec<String>,
-}
+impl Error for ReportParseError {}
 
-impl DiagnosticReport {
-    fn iter<'a>(&'a self) -> impl Iterator<Item = Vec<usize>> + 'a {
-        DiagnosticReportIter {
-            cursor: 0,
-            report: self,
+impl fmt::Display for ReportParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReportParseError::InvalidBit(c) => write!(f, "Invalid bit {:?}", c),
+            ReportParseError::MismatchedWidth(line) => {
+                write!(f, "Entry on line {} has the wrong width", line)
+            }
         }
     }
 }
 
+// which bit wins when a column has as many 1's as 0's
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TieBreak {
+    Zero,
+    One,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitCriteria {
+    MostCommon,
+    LeastCommon,
+}
+
+/**
+ * Every entry is packed into `limbs` u64's, column `c` (counting from the
+ * left, most significant bit first) lives at bit `c % 64` of limb `c / 64`.
+ * Entries are stored back to back in a single Vec.
+ */
 #[derive(Debug)]
-struct DiagnosticReportIter<'a> {
-    cursor: usize,
-    report: &'a DiagnosticReport,
+pub struct DiagnosticReport {
+    width: usize,
+    limbs: usize,
+    entries: Vec<u64>,
 }
 
-impl<'a> Iterator for DiagnosticReportIter<'a> {
-    type Item = Vec<usize>;
+impl DiagnosticReport {
+    pub fn len(&self) -> usize {
+        self.entries.len().checked_div(self.limbs).unwrap_or(0)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let entries_len = self.report.entries.len();
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-        if entries_len == 0 || self.cursor == self.report.entries[0].len() {
-            return None;
-        }
+    pub fn bit(&self, entry: usize, column: usize) -> bool {
+        self.entries[entry * self.limbs + column / 64] >> (column % 64) & 1 == 1
+    }
 
-        let mut result = Vec::with_capacity(entries_len);
+    // number of 1's in every column, in a single pass over the report
+    pub fn column_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.width];
 
-        for s in self.report.entries.iter() {
-            result.push(
-                s.chars()
-                    .nth(self.cursor)
-                    .expect("Failed to unwrap element")
-                    .to_digit(10)
-                    .unwrap() as usize,
-            );
+        for entry in 0..self.len() {
+            for (column, count) in counts.iter_mut().enumerate() {
+                *count += self.bit(entry, column) as usize;
+            }
         }
 
-        self.cursor += 1;
+        counts
+    }
+
+    pub fn gamma(&self, tie: TieBreak) -> BigUint {
+        let n = self.len();
+        let bits: Vec<bool> = self
+            .column_counts()
+            .iter()
+            .map(|&ones| pick_bit(ones, n - ones, BitCriteria::MostCommon, tie))
+            .collect();
 
-        Some(result)
+        to_biguint(&bits)
     }
-}
 
-#[aoc(day3, part1)]
-pub fn part1(input: &[String]) -> Result<u32, Box<dyn Error>> {
-    let report = DiagnosticReport {
-        entries: input.to_vec(),
-    };
+    // the complement of gamma, every column takes the bit gamma didn't
+    pub fn epsilon(&self, tie: TieBreak) -> BigUint {
+        let n = self.len();
+        let bits: Vec<bool> = self
+            .column_counts()
+            .iter()
+            .map(|&ones| !pick_bit(ones, n - ones, BitCriteria::MostCommon, tie))
+            .collect();
 
-    let mut gamma = 0;
-    let mut epsilon = 0;
+        to_biguint(&bits)
+    }
 
-    let width = input[0].len();
+    /**
+     * Narrows the report down column by column, keeping only the entries
+     * that have the bit picked by `criteria` among the remaining entries,
+     * until a single one is left. Each column costs one pass over the
+     * remaining entries to count and one to filter.
+     */
+    pub fn rating(&self, criteria: BitCriteria, tie: TieBreak) -> Option<BigUint> {
+        let mut remaining: Vec<usize> = (0..self.len()).collect();
+        let mut column = 0;
+
+        while remaining.len() > 1 && column < self.width {
+            let ones = remaining
+                .iter()
+                .filter(|&&entry| self.bit(entry, column))
+                .count();
+            let keep = pick_bit(ones, remaining.len() - ones, criteria, tie);
+
+            remaining.retain(|&entry| self.bit(entry, column) == keep);
+            column += 1;
+        }
 
-    for (i, n) in report.iter().enumerate() {
-        let result: isize = n
-            .iter()
-            .map(|v| match v {
-                0 => -1,
-                1 => 1,
-                _ => panic!("Unexpected value"),
-            })
-            .sum();
-
-        if result > 0 {
-            gamma += 1 << (width - i - 1);
-        } else {
-            epsilon += 1 << (width - i - 1);
+        match remaining.as_slice() {
+            [entry] => Some(self.value(*entry)),
+            _ => None,
         }
     }
 
-    Ok(gamma * epsilon)
+    fn value(&self, entry: usize) -> BigUint {
+        let bits: Vec<bool> = (0..self.width).map(|c| self.bit(entry, c)).collect();
+        to_biguint(&bits)
+    }
 }
 
-#[aoc(day3, part2)]
-pub fn part2(input: &[String]) -> usize {
-    let co2_rating = calculate_rating(&mut input.to_vec(), |c| c >= 0);
-    let o2_rating = calculate_rating(&mut input.to_vec(), |c| c < 0);
+fn pick_bit(ones: usize, zeros: usize, criteria: BitCriteria, tie: TieBreak) -> bool {
+    if ones == zeros {
+        return tie == TieBreak::One;
+    }
 
-    co2_rating * o2_rating
+    match criteria {
+        BitCriteria::MostCommon => ones > zeros,
+        BitCriteria::LeastCommon => ones < zeros,
+    }
 }
 
-fn calculate_rating<F: Fn(isize) -> bool>(entries: &mut Vec<String>, retain: F) -> usize {
-    let mut position = 0;
+// bits are most significant first
+fn to_biguint(bits: &[bool]) -> BigUint {
+    let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+
+    for (i, &bit) in bits.iter().rev().enumerate() {
+        bytes[i / 8] |= (bit as u8) << (i % 8);
+    }
 
-    while entries.len() > 1 {
-        let report = DiagnosticReport {
-            entries: entries.to_vec(),
-        };
+    BigUint::from_bytes_le(&bytes)
+}
 
-        // sum 1's and 0's, mapping 0's to -1 - a positive result means there
-        // were more 1's than 0's
-        let column_sum: isize = calculate_column_sum(report, position);
+#[aoc_generator(day3)]
+pub fn input_generator(input: &str) -> Result<DiagnosticReport, ReportParseError> {
+    let width = input.lines().next().map_or(0, |line| line.len());
+    let limbs = width.div_ceil(64);
+    let mut entries = Vec::new();
+
+    for (i, line) in input.lines().enumerate() {
+        if line.len() != width {
+            return Err(ReportParseError::MismatchedWidth(i + 1));
+        }
 
-        // filter entries so that only entries remain where the bit at
-        // `position` matches the most common bit
-        entries.retain(|x| {
-            let character = x.chars().nth(position).unwrap();
+        let mut word = vec![0u64; limbs];
 
-            if retain(column_sum) {
-                character == '1'
-            } else {
-                character == '0'
+        for (column, c) in line.chars().enumerate() {
+            match c {
+                '0' => {}
+                '1' => word[column / 64] |= 1 << (column % 64),
+                _ => return Err(ReportParseError::InvalidBit(c)),
             }
-        });
+        }
 
-        position += 1;
+        entries.extend(word);
     }
 
-    usize::from_str_radix(&entries[0], 2).expect("Expected a binary entry for rating")
+    Ok(DiagnosticReport {
+        width,
+        limbs,
+        entries,
+    })
 }
 
-fn calculate_column_sum(report: DiagnosticReport, position: usize) -> isize {
-    report
-        .iter()
-        .nth(position)
-        .unwrap()
-        .iter()
-        .map(|v| match v {
-            0 => -1,
-            1 => 1,
-            _ => panic!("Unexpected value"),
-        })
-        .sum()
+#[aoc(day3, part1)]
+pub fn part1(report: &DiagnosticReport) -> BigUint {
+    report.gamma(TieBreak::Zero) * report.epsilon(TieBreak::Zero)
 }
+
+#[aoc(day3, part2)]
+pub fn part2(report: &DiagnosticReport) -> Option<BigUint> {
+    let o2_rating = report.rating(BitCriteria::MostCommon, TieBreak::One)?;
+    let co2_rating = report.rating(BitCriteria::LeastCommon, TieBreak::Zero)?;
+
+    Some(o2_rating * co2_rating)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE: &str =
+        "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010";
+
     #[test]
     fn input() {
-        assert!(true)
+        let report = input_generator("101\n011").unwrap();
+
+        assert_eq!(report.len(), 2);
+        assert_eq!(report.column_counts(), vec![1, 1, 2]);
+        assert_eq!(
+            input_generator("101\n01").unwrap_err(),
+            ReportParseError::MismatchedWidth(2)
+        );
+        assert_eq!(
+            input_generator("1x1").unwrap_err(),
+            ReportParseError::InvalidBit('x')
+        );
     }
 
     #[test]
     fn sample1() {
-        let input = vec![
-            String::from("00100"),
-            String::from("11110"),
-            String::from("10110"),
-            String::from("10111"),
-            String::from("10101"),
-            String::from("01111"),
-            String::from("00111"),
-            String::from("11100"),
-            String::from("10000"),
-            String::from("11001"),
-            String::from("00010"),
-            String::from("01010"),
-        ];
-
-        assert_eq!(part1(&input).unwrap(), 198);
+        let report = input_generator(SAMPLE).unwrap();
+
+        assert_eq!(part1(&report), BigUint::from(198u32));
     }
 
     #[test]
     fn o2_rating() {
-        let mut input = vec![
-            String::from("00100"),
-            String::from("11110"),
-            String::from("10110"),
-            String::from("10111"),
-            String::from("10101"),
-            String::from("01111"),
-            String::from("00111"),
-            String::from("11100"),
-            String::from("10000"),
-            String::from("11001"),
-            String::from("00010"),
-            String::from("01010"),
-        ];
-
-        assert_eq!(calculate_rating(&mut input, |x| x >= 0), 23);
+        let report = input_generator(SAMPLE).unwrap();
+
+        assert_eq!(
+            report.rating(BitCriteria::MostCommon, TieBreak::One),
+            Some(BigUint::from(23u32))
+        );
     }
 
     #[test]
     fn co2_rating() {
-        let mut input = vec![
-            String::from("00100"),
-            String::from("11110"),
-            String::from("10110"),
-            String::from("10111"),
-            String::from("10101"),
-            String::from("01111"),
-            String::from("00111"),
-            String::from("11100"),
-            String::from("10000"),
-            String::from("11001"),
-            String::from("00010"),
-            String::from("01010"),
-        ];
-        assert_eq!(calculate_rating(&mut input, |x| x < 0), 10);
+        let report = input_generator(SAMPLE).unwrap();
+
+        assert_eq!(
+            report.rating(BitCriteria::LeastCommon, TieBreak::Zero),
+            Some(BigUint::from(10u32))
+        );
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day3.txt");
+        let report = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&report), BigUint::from(3429254u32));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day3.txt");
+        let report = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&report), Some(BigUint::from(5410338u32)));
+    }
+
+    #[test]
+    fn wide_words_test() {
+        // 130 bits, straddling three limbs
+        let a = format!("1{}1", "0".repeat(128));
+        let b = format!("1{}0", "1".repeat(128));
+        let c = format!("0{}1", "1".repeat(128));
+        let report = input_generator(&[a, b, c].join("\n")).unwrap();
+        let top = BigUint::from(1u32) << 129;
+        let bottom = BigUint::from(1u32);
+        let middle = (BigUint::from(1u32) << 129) - 2u32;
+
+        assert_eq!(report.gamma(TieBreak::Zero), &top + &middle + &bottom);
+        assert_eq!(report.epsilon(TieBreak::Zero), BigUint::from(0u32));
+        assert_eq!(
+            report.rating(BitCriteria::MostCommon, TieBreak::One),
+            Some(&top + &middle)
+        );
+    }
+
+    #[test]
+    fn tie_break_test() {
+        // every column of a two entry report is a tie
+        let report = input_generator("10\n01").unwrap();
+
+        assert_eq!(report.gamma(TieBreak::Zero), BigUint::from(0u32));
+        assert_eq!(report.gamma(TieBreak::One), BigUint::from(3u32));
+        assert_eq!(
+            report.rating(BitCriteria::LeastCommon, TieBreak::One),
+            Some(BigUint::from(2u32))
+        );
     }
 }
@@ -2,17 +2,18 @@ use std::error::Error;
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Default, Clone)]
 pub struct Bingo {
     picks: Vec<usize>,
     boards: Vec<Board>,
-    winners: Vec<Board>,
-    dimension: usize,
+    rules: Vec<WinRule>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Board {
     spaces: Vec<Space>,
+    rows: usize,
+    cols: usize,
     winner: bool,
     last_pick: Option<usize>,
 }
@@ -25,50 +26,144 @@ pub struct Space {
     col: usize,
 }
 
+// a board wins as soon as any of the game's rules is satisfied
+#[derive(Debug, Clone)]
+pub enum WinRule {
+    RowsAndColumns,
+    Diagonals, // only square boards have diagonals
+    FourCorners,
+    FullCard,
+    Custom(fn(&Board) -> bool),
+}
+
+// everything that happened on a single pick, in board order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Turn {
+    pub pick: usize,
+    pub winners: Vec<Win>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Win {
+    pub board: usize, // index of the board in the order they were dealt
+    pub score: usize,
+}
+
 impl Bingo {
-    fn play(&mut self) -> &mut Bingo {
-        for pick in self.picks.clone() {
-            self.mark_spaces(pick);
-            self.check_winners(pick);
+    pub fn with_rules(mut self, rules: Vec<WinRule>) -> Bingo {
+        self.rules = rules;
+        self
+    }
+
+    // every pick in order, along with the boards that won on it
+    pub fn timeline(&self) -> Vec<Turn> {
+        self.play(self.picks.len()).1
+    }
+
+    // the state of every board after the first `turns` picks
+    pub fn replay(&self, turns: usize) -> Vec<Board> {
+        self.play(turns).0
+    }
+
+    pub fn winners(&self) -> impl Iterator<Item = Win> {
+        self.timeline().into_iter().flat_map(|turn| turn.winners)
+    }
+
+    fn play(&self, turns: usize) -> (Vec<Board>, Vec<Turn>) {
+        let mut boards = self.boards.clone();
+        let mut timeline = Vec::with_capacity(turns);
+
+        for &pick in self.picks.iter().take(turns) {
+            mark_spaces(&mut boards, pick);
+
+            timeline.push(Turn {
+                pick,
+                winners: self.check_winners(&mut boards, pick),
+            });
         }
 
-        self
+        (boards, timeline)
     }
 
-    fn mark_spaces(&mut self, pick: usize) {
-        for board in &mut self.boards {
+    fn check_winners(&self, boards: &mut [Board], pick: usize) -> Vec<Win> {
+        let mut winners = Vec::new();
+
+        for (i, board) in boards.iter_mut().enumerate() {
             if board.winner {
                 continue;
             }
 
-            for space in &mut board.spaces {
-                if space.value == pick {
-                    space.picked = true;
-                }
+            if self.rules.iter().any(|rule| rule.is_win(board)) {
+                board.winner = true;
+                board.last_pick = Some(pick);
+
+                winners.push(Win {
+                    board: i,
+                    score: sum_unmarked_spaces(board) * pick,
+                });
             }
         }
+
+        winners
     }
+}
 
-    fn check_winners(&mut self, pick: usize) {
-        for board in &mut self.boards {
-            if board.winner {
-                continue;
+fn mark_spaces(boards: &mut [Board], pick: usize) {
+    for board in boards {
+        if board.winner {
+            continue;
+        }
+
+        for space in &mut board.spaces {
+            if space.value == pick {
+                space.picked = true;
             }
+        }
+    }
+}
 
-            for i in 0..self.dimension {
-                if check_row_win(board, i) || check_col_win(board, i) {
-                    board.winner = true;
-                    board.last_pick = Some(pick);
+impl WinRule {
+    pub fn is_win(&self, board: &Board) -> bool {
+        match self {
+            WinRule::RowsAndColumns => {
+                (0..board.rows).any(|i| check_row_win(board, i))
+                    || (0..board.cols).any(|i| check_col_win(board, i))
+            }
+            WinRule::Diagonals => {
+                let n = board.rows;
 
-                    self.winners.push(board.clone());
+                board.rows == board.cols
+                    && ((0..n).all(|i| board.is_picked(i, i))
+                        || (0..n).all(|i| board.is_picked(i, n - 1 - i)))
+            }
+            WinRule::FourCorners => {
+                let (last_row, last_col) = (board.rows - 1, board.cols - 1);
 
-                    break;
-                }
+                board.is_picked(0, 0)
+                    && board.is_picked(0, last_col)
+                    && board.is_picked(last_row, 0)
+                    && board.is_picked(last_row, last_col)
             }
+            WinRule::FullCard => board.spaces.iter().all(|s| s.picked),
+            WinRule::Custom(rule) => rule(board),
         }
     }
 }
 
+impl Board {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_picked(&self, row: usize, col: usize) -> bool {
+        self.spaces[row * self.cols + col].picked
+    }
+}
+
 #[derive(Debug)]
 pub struct BoardParseError;
 
@@ -84,22 +179,35 @@ impl FromStr for Board {
     type Err = BoardParseError;
 
     fn from_str(board: &str) -> Result<Board, Self::Err> {
-        let spaces = board
-            .split("\n")
-            .enumerate()
-            .flat_map(|(y, row)| {
-                row.split_whitespace()
-                    .enumerate()
-                    .map(move |(x, space)| Space {
-                        value: space.parse().unwrap(),
-                        picked: false,
-                        row: y,
-                        col: x,
-                    })
-            })
-            .collect();
+        let mut spaces = Vec::new();
+        let mut cols = None;
+        let mut rows = 0;
+
+        for (y, row) in board.lines().enumerate() {
+            let values = row
+                .split_whitespace()
+                .map(|space| space.parse().map_err(|_| BoardParseError))
+                .collect::<Result<Vec<usize>, BoardParseError>>()?;
+
+            // boards can be any size, as long as they're rectangular
+            if values.is_empty() || *cols.get_or_insert(values.len()) != values.len() {
+                return Err(BoardParseError);
+            }
+
+            spaces.extend(values.into_iter().enumerate().map(|(x, value)| Space {
+                value,
+                picked: false,
+                row: y,
+                col: x,
+            }));
+
+            rows += 1;
+        }
+
         Ok(Board {
             spaces,
+            rows,
+            cols: cols.ok_or(BoardParseError)?,
             winner: false,
             last_pick: None,
         })
@@ -108,7 +216,7 @@ impl FromStr for Board {
 
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let rows = self.spaces.chunks(5);
+        let rows = self.spaces.chunks(self.cols);
         let mut display_str = String::from("");
 
         for row in rows {
@@ -116,7 +224,7 @@ impl fmt::Display for Board {
                 display_str.push_str(&format!("{} ", if space.picked { "x" } else { "·" }));
             }
 
-            display_str.push_str(&"\n");
+            display_str.push('\n');
         }
 
         write!(f, "{}", display_str)
@@ -140,57 +248,30 @@ fn check_col_win(board: &Board, index: usize) -> bool {
 }
 
 #[aoc_generator(day4)]
-pub fn input_generator(input: &str) -> Bingo {
-    let mut bingo = Bingo {
-        dimension: 5,
-        ..Bingo::default()
-    };
-
-    let (picks, boards) = input
-        .split_once("\n\n")
-        .expect("Could not split picks from boards");
-
-    bingo.picks = picks
-        .split(",")
-        .map(|n| n.parse().expect("Failed to parse pick"))
-        .collect();
-
-    bingo.boards = boards
-        .split("\n\n")
-        .map(|board| board.parse().expect("Failed to parse board"))
-        .collect();
-
-    bingo
+pub fn input_generator(input: &str) -> Result<Bingo, Box<dyn Error>> {
+    let (picks, boards) = input.split_once("\n\n").ok_or(BoardParseError)?;
+
+    Ok(Bingo {
+        picks: picks
+            .split(',')
+            .map(|n| n.parse())
+            .collect::<Result<_, _>>()?,
+        boards: boards
+            .split("\n\n")
+            .map(|board| board.parse())
+            .collect::<Result<_, _>>()?,
+        rules: vec![WinRule::RowsAndColumns],
+    })
 }
 
 #[aoc(day4, part1)]
-pub fn part1(bingo: &Bingo) -> usize {
-    let mut bingo = bingo.clone();
-
-    bingo.play();
-
-    if let Some(winner) = &bingo.winners.first() {
-        println!("{}", winner);
-        sum_unmarked_spaces(winner) * winner.last_pick.expect("Winning board missing last_pick")
-    } else {
-        println!("A winner was not found!");
-        0
-    }
+pub fn part1(bingo: &Bingo) -> Option<usize> {
+    bingo.winners().next().map(|winner| winner.score)
 }
 
 #[aoc(day4, part2)]
-pub fn part2(bingo: &Bingo) -> usize {
-    let mut bingo = bingo.clone();
-
-    bingo.play();
-
-    if let Some(winner) = &bingo.winners.last() {
-        println!("{}", winner);
-        sum_unmarked_spaces(winner) * winner.last_pick.expect("Winning board missing last_pick")
-    } else {
-        println!("A winner was not found!");
-        0
-    }
+pub fn part2(bingo: &Bingo) -> Option<usize> {
+    bingo.winners().last().map(|winner| winner.score)
 }
 
 fn sum_unmarked_spaces(board: &Board) -> usize {
@@ -227,7 +308,8 @@ mod tests {
         18  8 23 26 20
         22 11 13  6  5
          2  0 12  3  7",
-        );
+        )
+        .unwrap();
 
         assert_eq!(
             bingo.picks,
@@ -243,4 +325,80 @@ mod tests {
         assert_eq!(bingo.boards[1].spaces.len(), 25);
         assert_eq!(bingo.boards[2].spaces.len(), 25);
     }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day4.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input), Some(16674));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day4.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input), Some(7075));
+    }
+
+    #[test]
+    fn timeline_test() {
+        let bingo = input_generator("5,1,9,2,4,7,3\n\n1 2\n3 4\n\n5 6 7\n8 9 1").unwrap();
+        let timeline = bingo.timeline();
+
+        assert_eq!(timeline.len(), 7);
+        assert_eq!(timeline[0].winners, vec![]);
+        assert_eq!(
+            timeline[3].winners,
+            vec![Win {
+                board: 0,
+                score: 7 * 2
+            }]
+        );
+        assert_eq!(
+            timeline[5].winners,
+            vec![Win {
+                board: 1,
+                score: 14 * 7
+            }]
+        );
+        assert_eq!(bingo.replay(4)[1].to_string(), "x · · \n· x x \n");
+
+        // the replay stops marking a board once it has won
+        assert_eq!(bingo.replay(5)[0].to_string(), "x x \n· · \n");
+    }
+
+    #[test]
+    fn win_rules_test() {
+        let board = "1 2 3\n4 5 6\n7 8 9";
+        let game = |picks: &str, rule: WinRule| {
+            input_generator(&format!("{}\n\n{}", picks, board))
+                .unwrap()
+                .with_rules(vec![rule])
+                .winners()
+                .next()
+        };
+
+        assert_eq!(game("1,5,4,9", WinRule::RowsAndColumns), None);
+        assert_eq!(
+            game("1,5,4,9", WinRule::Diagonals).unwrap().score,
+            9 * (2 + 3 + 6 + 7 + 8)
+        );
+        assert_eq!(game("3,5,7", WinRule::Diagonals).unwrap().score, 7 * 30);
+        assert_eq!(
+            game("1,3,5,7,9", WinRule::FourCorners).unwrap().score,
+            9 * 20
+        );
+        assert_eq!(game("1,2,3,4,5,6,7,8", WinRule::FullCard), None);
+        assert_eq!(
+            game(
+                "2,4,6,8",
+                WinRule::Custom(|b| b.is_picked(1, 0) && b.is_picked(2, 1))
+            )
+            .unwrap()
+            .score,
+            8 * 25
+        );
+    }
 }
@@ -1,4 +1,13 @@
-use std::{collections::HashMap, error::Error, fmt, str::FromStr};
+use itertools::Itertools;
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fmt,
+    str::FromStr,
+};
+
+type Point = (i64, i64); // x, y
+type Span = (i64, i64); // inclusive range of positions along a line
 
 #[derive(Debug)]
 struct Line(
@@ -24,93 +33,455 @@ impl FromStr for Line {
 
     fn from_str(string: &str) -> Result<Line, Self::Err> {
         let (start, end) = string.split_once(" -> ").ok_or(LineParseError)?;
-        let (x1, y1) = start.split_once(",").ok_or(LineParseError)?;
-        let (x2, y2) = end.split_once(",").ok_or(LineParseError)?;
+        let (x1, y1) = start.split_once(',').ok_or(LineParseError)?;
+        let (x2, y2) = end.split_once(',').ok_or(LineParseError)?;
+        let line = Line(x1.parse()?, y1.parse()?, x2.parse()?, y2.parse()?);
+
+        // vents only ever line up horizontally, vertically or at exactly 45 degrees
+        line.direction().ok_or(LineParseError)?;
 
-        Ok(Line(x1.parse()?, y1.parse()?, x2.parse()?, y2.parse()?))
+        Ok(line)
     }
 }
 
-fn point_range_vh(a: u32, b: u32) -> impl DoubleEndedIterator<Item = u32> {
-    if a > b {
-        b..a + 1
-    } else {
-        a..b + 1
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Direction {
+    Horizontal,
+    Vertical,
+    Diagonal,     // x and y both increase
+    AntiDiagonal, // x increases as y decreases
 }
 
-// ??????? bunch of dumb shit dealing with normalizing point iterators as ranges
-fn point_range_diag(line: &Line) -> Box<dyn Iterator<Item = (u32, u32)>> {
-    let Line(x1, y1, x2, y2) = *line;
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Horizontal,
+    Direction::Vertical,
+    Direction::Diagonal,
+    Direction::AntiDiagonal,
+];
+
+/**
+ * Every line in a given direction is identified by a key `a * x + b * y`,
+ * which is the same for every point on the line. Points are then identified
+ * within their line by a position, which is just one of their coordinates.
+ */
+impl Direction {
+    fn coefficients(self) -> (i64, i64) {
+        match self {
+            Direction::Horizontal => (0, 1),
+            Direction::Vertical => (1, 0),
+            Direction::Diagonal => (1, -1),
+            Direction::AntiDiagonal => (1, 1),
+        }
+    }
+
+    fn key(self, (x, y): Point) -> i64 {
+        let (a, b) = self.coefficients();
+        a * x + b * y
+    }
+
+    fn position(self, (x, y): Point) -> i64 {
+        match self {
+            Direction::Vertical => y,
+            _ => x,
+        }
+    }
+
+    fn point(self, key: i64, position: i64) -> Point {
+        match self {
+            Direction::Horizontal => (position, key),
+            Direction::Vertical => (key, position),
+            Direction::Diagonal => (position, position - key),
+            Direction::AntiDiagonal => (position, key - position),
+        }
+    }
+
+    fn is_diagonal(self) -> bool {
+        matches!(self, Direction::Diagonal | Direction::AntiDiagonal)
+    }
+
+    // where lines `a` and `b` of two different directions cross, if that's on the grid
+    fn intersect(self, a: i64, other: Direction, b: i64) -> Option<Point> {
+        let (a1, b1) = self.coefficients();
+        let (a2, b2) = other.coefficients();
+        let det = a1 * b2 - a2 * b1;
+        let x = a * b2 - b * b1;
+        let y = a1 * b - a2 * a;
+
+        if x % det != 0 || y % det != 0 {
+            return None;
+        }
+
+        Some((x / det, y / det))
+    }
+}
 
-    let h = point_range_vh(x1, x2);
-    let v = point_range_vh(y1, y2);
+impl Line {
+    fn direction(&self) -> Option<Direction> {
+        let Line(x1, y1, x2, y2) = *self;
+        let dx = x2 as i64 - x1 as i64;
+        let dy = y2 as i64 - y1 as i64;
 
-    // xs are reversed, so we need to reverse ys
-    if x1 > x2 && y1 < y2 {
-        return Box::new(h.zip(v.rev()));
+        if dy == 0 {
+            Some(Direction::Horizontal)
+        } else if dx == 0 {
+            Some(Direction::Vertical)
+        } else if dx == dy {
+            Some(Direction::Diagonal)
+        } else if dx == -dy {
+            Some(Direction::AntiDiagonal)
+        } else {
+            None
+        }
     }
 
-    // ys are reversed, so we need to reverse xs
-    if y1 > y2 && x1 < x2 {
-        return Box::new(h.rev().zip(v));
+    fn endpoints(&self) -> (Point, Point) {
+        let Line(x1, y1, x2, y2) = *self;
+        ((x1 as i64, y1 as i64), (x2 as i64, y2 as i64))
     }
+}
 
-    Box::new(h.zip(v))
+// a maximal run of covered points along a single line
+#[derive(Debug)]
+struct Run {
+    direction: Direction,
+    key: i64,
+    span: Span,
+}
+
+impl Run {
+    // the range of keys of lines in `other` direction that this run crosses
+    fn key_range(&self, other: Direction) -> Span {
+        let a = other.key(self.direction.point(self.key, self.span.0));
+        let b = other.key(self.direction.point(self.key, self.span.1));
+
+        (a.min(b), a.max(b))
+    }
 }
 
 #[aoc_generator(day5)]
-fn input_generator(input: &str) -> Vec<Line> {
-    input.split("\n").map(|l| l.parse().unwrap()).collect()
+fn input_generator(input: &str) -> Result<Vec<Line>, Box<dyn Error>> {
+    input.lines().map(|l| l.parse()).collect()
 }
 
 #[aoc(day5, part1)]
-fn part1(lines: &[Line]) -> usize {
-    let mut counts: HashMap<(u32, u32), u32> = HashMap::new();
+fn part1(lines: &[Line]) -> u64 {
+    count_overlaps(lines, false)
+}
+
+#[aoc(day5, part2)]
+fn part2(lines: &[Line]) -> u64 {
+    count_overlaps(lines, true)
+}
+
+/**
+ * Counts the points covered by at least two lines without visiting every
+ * point along them.
+ *
+ * Collinear lines are merged into runs along their shared line: the points
+ * covered twice or more by the same direction are counted straight from the
+ * lengths of those runs. Lines of different directions can only ever share a
+ * single point, so those crossings are found separately and checked against
+ * the runs to avoid counting a point twice. Memory is proportional to the
+ * number of lines plus the number of crossings, never their length.
+ */
+fn count_overlaps(lines: &[Line], diagonals: bool) -> u64 {
+    let mut spans: HashMap<(Direction, i64), Vec<Span>> = HashMap::new();
 
     for line in lines {
-        let Line(x1, y1, x2, y2) = &line;
+        let direction = line.direction().expect("lines are validated when parsed");
 
-        if x1 == x2 {
-            // vertical
-            for y in point_range_vh(*y1, *y2) {
-                *(counts.entry((*x1, y)).or_insert(0)) += 1;
-            }
-        } else if y1 == y2 {
-            // horizontal
-            for x in point_range_vh(*x1, *x2) {
-                *(counts.entry((x, *y1)).or_insert(0)) += 1;
-            }
+        if direction.is_diagonal() && !diagonals {
+            continue;
         }
+
+        let (start, end) = line.endpoints();
+        let (a, b) = (direction.position(start), direction.position(end));
+
+        spans
+            .entry((direction, direction.key(start)))
+            .or_default()
+            .push((a.min(b), a.max(b)));
     }
 
-    counts.into_values().filter(|&v| v > 1).count()
+    let mut runs: Vec<Run> = Vec::new();
+    let mut covered: HashMap<(Direction, i64), Vec<Span>> = HashMap::new();
+    let mut overlapping: HashMap<(Direction, i64), Vec<Span>> = HashMap::new();
+
+    for ((direction, key), spans) in spans {
+        let merged = spans_with_depth(&spans, 1);
+
+        runs.extend(merged.iter().map(|&span| Run {
+            direction,
+            key,
+            span,
+        }));
+
+        covered.insert((direction, key), merged);
+        overlapping.insert((direction, key), spans_with_depth(&spans, 2));
+    }
+
+    let mut total: u64 = overlapping
+        .values()
+        .flatten()
+        .map(|(lo, hi)| (hi - lo + 1) as u64)
+        .sum();
+
+    find_crossings(&runs, |first, second, point| {
+        let mut crossing = DIRECTIONS
+            .iter()
+            .filter(|&&direction| in_spans(&covered, direction, point));
+
+        // where three or more directions meet, every pair of them finds the point, so only the
+        // first pair gets to count it
+        if crossing.next() != Some(&first) || crossing.next() != Some(&second) {
+            return;
+        }
+
+        let overlaps = DIRECTIONS
+            .iter()
+            .filter(|&&direction| in_spans(&overlapping, direction, point))
+            .count() as u64;
+
+        // a crossing only adds a point if no single direction already counted it, and any point
+        // that overlaps in several directions at once was counted once too many
+        if overlaps == 0 {
+            total += 1;
+        } else {
+            total -= overlaps - 1;
+        }
+    });
+
+    total
 }
 
-#[aoc(day5, part2)]
-fn part2(lines: &[Line]) -> usize {
-    let mut counts: HashMap<(u32, u32), u32> = HashMap::new();
+// merges spans along one line into the ranges covered by at least `depth` of them
+fn spans_with_depth(spans: &[Span], depth: i32) -> Vec<Span> {
+    let mut events: Vec<(i64, i32)> = spans
+        .iter()
+        .flat_map(|&(lo, hi)| [(lo, 1), (hi + 1, -1)])
+        .collect();
+    let mut result = Vec::new();
+    let mut current = 0;
+    let mut start = 0;
 
-    for line in lines {
-        let Line(x1, y1, x2, y2) = &line;
+    events.sort_unstable();
+
+    for (position, changes) in &events.into_iter().group_by(|&(position, _)| position) {
+        let before = current;
 
-        if x1 == x2 {
-            // vertical
-            for y in point_range_vh(*y1, *y2) {
-                *(counts.entry((*x1, y)).or_insert(0)) += 1;
+        current += changes.map(|(_, change)| change).sum::<i32>();
+
+        if before < depth && current >= depth {
+            start = position;
+        } else if before >= depth && current < depth {
+            result.push((start, position - 1));
+        }
+    }
+
+    result
+}
+
+/**
+ * Sweeps over each pair of directions, treating runs of the first direction as
+ * horizontal and runs of the second as vertical in a coordinate system made up
+ * of the two directions' line keys. Runs of the first direction that are
+ * active when a run of the second direction is reached are exactly the ones
+ * it crosses, so only real crossings are ever visited.
+ */
+// calls `visit` with every point where runs of two different directions cross, once per pair
+fn find_crossings(runs: &[Run], mut visit: impl FnMut(Direction, Direction, Point)) {
+    for (i, &first) in DIRECTIONS.iter().enumerate() {
+        for &second in &DIRECTIONS[i + 1..] {
+            // (second key, event kind, ..) so inserts sort before queries before removals
+            let mut events: Vec<(i64, u8, i64, i64)> = Vec::new();
+
+            for run in runs.iter().filter(|r| r.direction == first) {
+                let (lo, hi) = run.key_range(second);
+
+                events.push((lo, 0, run.key, 0));
+                events.push((hi, 2, run.key, 0));
+            }
+
+            for run in runs.iter().filter(|r| r.direction == second) {
+                let (lo, hi) = run.key_range(first);
+
+                events.push((run.key, 1, lo, hi));
             }
-        } else if y1 == y2 {
-            // horizontal
-            for x in point_range_vh(*x1, *x2) {
-                *(counts.entry((x, *y1)).or_insert(0)) += 1;
+
+            events.sort_unstable();
+
+            let mut active: BTreeSet<i64> = BTreeSet::new();
+
+            for (key, kind, a, b) in events {
+                match kind {
+                    0 => {
+                        active.insert(a);
+                    }
+                    1 => active
+                        .range(a..=b)
+                        .filter_map(|&other| first.intersect(other, second, key))
+                        .for_each(|point| visit(first, second, point)),
+                    _ => {
+                        active.remove(&a);
+                    }
+                }
             }
+        }
+    }
+}
+
+fn in_spans(
+    spans: &HashMap<(Direction, i64), Vec<Span>>,
+    direction: Direction,
+    point: Point,
+) -> bool {
+    let position = direction.position(point);
+
+    spans
+        .get(&(direction, direction.key(point)))
+        .is_some_and(|spans| {
+            let i = spans.partition_point(|&(lo, _)| lo <= position);
+            i > 0 && spans[i - 1].1 >= position
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "0,9 -> 5,9\n8,0 -> 0,8\n9,4 -> 3,4\n2,2 -> 2,1\n7,0 -> 7,4\n6,4 -> 2,0\n0,9 -> 2,9\n3,4 -> 1,4\n0,0 -> 8,8\n5,5 -> 8,2";
+
+    fn point_range_vh(a: u32, b: u32) -> impl DoubleEndedIterator<Item = u32> {
+        if a > b {
+            b..a + 1
         } else {
-            // diagonal
-            for (x, y) in point_range_diag(line) {
-                *(counts.entry((x, y)).or_insert(0)) += 1;
+            a..b + 1
+        }
+    }
+
+    // ??????? bunch of dumb shit dealing with normalizing point iterators as ranges
+    fn point_range_diag(line: &Line) -> Box<dyn Iterator<Item = (u32, u32)>> {
+        let Line(x1, y1, x2, y2) = *line;
+
+        let h = point_range_vh(x1, x2);
+        let v = point_range_vh(y1, y2);
+
+        // xs are reversed, so we need to reverse ys
+        if x1 > x2 && y1 < y2 {
+            return Box::new(h.zip(v.rev()));
+        }
+
+        // ys are reversed, so we need to reverse xs
+        if y1 > y2 && x1 < x2 {
+            return Box::new(h.rev().zip(v));
+        }
+
+        Box::new(h.zip(v))
+    }
+
+    // the original point-by-point implementation, used to cross check the sweep
+    fn rasterize_overlaps(lines: &[Line], diagonals: bool) -> u64 {
+        let mut counts: HashMap<(u32, u32), u32> = HashMap::new();
+
+        for line in lines {
+            let Line(x1, y1, x2, y2) = *line;
+
+            if x1 == x2 {
+                // vertical
+                for y in point_range_vh(y1, y2) {
+                    *(counts.entry((x1, y)).or_insert(0)) += 1;
+                }
+            } else if y1 == y2 {
+                // horizontal
+                for x in point_range_vh(x1, x2) {
+                    *(counts.entry((x, y1)).or_insert(0)) += 1;
+                }
+            } else if diagonals {
+                for (x, y) in point_range_diag(line) {
+                    *(counts.entry((x, y)).or_insert(0)) += 1;
+                }
             }
         }
+
+        counts.into_values().filter(|&v| v > 1).count() as u64
     }
 
-    counts.into_values().filter(|&v| v > 1).count()
+    #[test]
+    fn example_test() {
+        let input = input_generator(EXAMPLE).unwrap();
+
+        assert_eq!(part1(&input), 5);
+        assert_eq!(part2(&input), 12);
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day5.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input), 6283);
+        assert_eq!(part1(&input), rasterize_overlaps(&input, false));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day5.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input), 18864);
+        assert_eq!(part2(&input), rasterize_overlaps(&input, true));
+    }
+
+    #[test]
+    fn cross_check_test() {
+        // small, crowded grids so that lines overlap and cross in every possible way
+        let mut seed: u64 = 2021;
+        let mut next = |n: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % n) as u32
+        };
+
+        for _ in 0..50 {
+            let lines: Vec<Line> = (0..40)
+                .map(|_| {
+                    let (x, y, len) = (next(20), next(20), next(12));
+
+                    match next(4) {
+                        0 => Line(x, y, x + len, y),
+                        1 => Line(x, y + len, x, y),
+                        2 => Line(x + len, y + len, x, y),
+                        _ => Line(x, y + len, x + len, y),
+                    }
+                })
+                .collect();
+
+            assert_eq!(
+                count_overlaps(&lines, false),
+                rasterize_overlaps(&lines, false)
+            );
+            assert_eq!(
+                count_overlaps(&lines, true),
+                rasterize_overlaps(&lines, true)
+            );
+        }
+    }
+
+    #[test]
+    fn huge_coordinates_test() {
+        let max = u32::MAX;
+        let lines = vec![
+            Line(0, 0, max, 0),
+            Line(max, 0, 5, 0),
+            Line(7, max, 7, 0),
+            Line(0, 0, max, max),
+            Line(max, max, 0, max),
+        ];
+
+        // the overlap along the bottom, the vertical line crossing the top and the diagonal, and
+        // both ends of the diagonal
+        assert_eq!(part2(&lines), (max as u64 - 5 + 1) + 2 + 2);
+        assert!(input_generator("0,0 -> 1,2").is_err());
+    }
 }
@@ -1,66 +1,325 @@
-#[aoc_generator(day6)]
-pub fn input_generator(input: &str) -> [u64; 9] {
-    let mut fish_counts = [0u64; 9];
+use num_bigint::BigUint;
+use std::error::Error;
+use std::fmt;
 
-    let population: Vec<u64> = input.split(",").map(|n| n.parse().unwrap()).collect();
+// the puzzle's fish reset to 6 after spawning, newborns start at 8
+const RESET_TIMER: usize = 6;
+const NEWBORN_TIMER: usize = 8;
 
-    for f in population {
-        fish_counts[f as usize] += 1;
-    }
+type Matrix = Vec<Vec<BigUint>>;
 
-    fish_counts
+#[derive(Debug, PartialEq)]
+pub enum PopulationError {
+    Parse,
+    TimerOutOfRange(usize),
+    CountsLength { expected: usize, found: usize }, // one count per timer value
 }
 
-#[aoc(day6, part1)]
-pub fn part1(fish_counts: &[u64; 9]) -> u64 {
-    let mut fish_counts = fish_counts.clone();
+impl Error for PopulationError {}
 
-    for _ in 0..80 {
-        simulate(&mut fish_counts);
+impl fmt::Display for PopulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PopulationError::Parse => write!(f, "Error parsing lanternfish timers"),
+            PopulationError::TimerOutOfRange(timer) => {
+                write!(f, "Timer {} is longer than any timer in the model", timer)
+            }
+            PopulationError::CountsLength { expected, found } => write!(
+                f,
+                "Expected {} fish counts, one per timer, but got {}",
+                expected, found
+            ),
+        }
     }
+}
 
-    fish_counts.iter().sum()
+/**
+ * Fish are bucketed by their timer. Every day each bucket moves down one
+ * timer, and the fish at 0 reset to `reset` while spawning the same number of
+ * fish at `newborn`. Since a day is a linear map over the buckets, `n` days
+ * is that map's matrix raised to the `n`th power, which takes O(log n) matrix
+ * multiplications instead of `n` steps.
+ */
+#[derive(Debug, Clone)]
+pub struct PopulationModel {
+    reset: usize,
+    newborn: usize,
+    modulus: Option<BigUint>,
 }
 
-#[aoc(day6, part2)]
-pub fn part2(fish_counts: &[u64; 9]) -> u64 {
-    let mut fish_counts = fish_counts.clone();
+impl PopulationModel {
+    pub fn new(reset: usize, newborn: usize) -> Self {
+        PopulationModel {
+            reset,
+            newborn,
+            modulus: None,
+        }
+    }
 
-    for _ in 0..256 {
-        simulate(&mut fish_counts);
+    // only track populations modulo `modulus`, exact counts gain a digit every month or so, which
+    // adds up over billions of days
+    pub fn with_modulus(mut self, modulus: BigUint) -> Self {
+        self.modulus = Some(modulus);
+        self
     }
 
-    fish_counts.iter().sum()
-}
+    pub fn timers(&self) -> usize {
+        self.reset.max(self.newborn) + 1
+    }
+
+    // number of fish for each timer value
+    pub fn census(&self, timers: &[usize]) -> Result<Vec<BigUint>, PopulationError> {
+        let mut counts = vec![BigUint::default(); self.timers()];
+
+        for &timer in timers {
+            *counts
+                .get_mut(timer)
+                .ok_or(PopulationError::TimerOutOfRange(timer))? += 1u32;
+        }
+
+        Ok(counts)
+    }
+
+    // advances the population by a single day
+    pub fn step(&self, counts: &mut Vec<BigUint>) -> Result<(), PopulationError> {
+        self.check(counts)?;
+
+        let reproducing = counts.remove(0);
+
+        counts.push(BigUint::default());
+        counts[self.reset] += &reproducing;
+        counts[self.newborn] += reproducing;
+
+        Ok(())
+    }
+
+    // per-timer populations on each of the given days, in ascending day order
+    pub fn populations(
+        &self,
+        counts: &[BigUint],
+        days: &[u64],
+    ) -> Result<Vec<(u64, Vec<BigUint>)>, PopulationError> {
+        self.check(counts)?;
+
+        let mut days = days.to_vec();
+        let mut counts = counts.to_vec();
+        let mut today = 0;
+
+        days.sort_unstable();
+        days.dedup();
 
-fn simulate(fish_counts: &mut [u64]) {
-    let reproducing = fish_counts[0];
+        Ok(days
+            .into_iter()
+            .map(|day| {
+                counts = self.apply(&self.power(day - today), &counts);
+                today = day;
 
-    for i in 0..8 {
-        fish_counts[i] = fish_counts[i + 1]
+                (day, counts.clone())
+            })
+            .collect())
     }
 
-    fish_counts[6] += reproducing;
-    fish_counts[8] = reproducing;
+    pub fn total(&self, counts: &[BigUint], day: u64) -> Result<BigUint, PopulationError> {
+        let total: BigUint = self.populations(counts, &[day])?[0].1.iter().sum();
+        Ok(self.reduce(total))
+    }
+
+    // counts from another model would otherwise be silently cut short or padded out
+    fn check(&self, counts: &[BigUint]) -> Result<(), PopulationError> {
+        if counts.len() != self.timers() {
+            return Err(PopulationError::CountsLength {
+                expected: self.timers(),
+                found: counts.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn transition(&self) -> Matrix {
+        let n = self.timers();
+        let mut matrix = vec![vec![BigUint::default(); n]; n];
+
+        // everybody moves down a timer...
+        for (i, row) in matrix.iter_mut().enumerate().take(n - 1) {
+            row[i + 1] = BigUint::from(1u32);
+        }
+
+        // ...except the fish at 0, who reset and spawn
+        matrix[self.reset][0] += 1u32;
+        matrix[self.newborn][0] += 1u32;
+
+        matrix
+    }
+
+    fn power(&self, mut exponent: u64) -> Matrix {
+        let mut base = self.transition();
+        let mut result = identity(self.timers());
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = self.multiply(&result, &base);
+            }
+
+            exponent >>= 1;
+
+            if exponent > 0 {
+                base = self.multiply(&base, &base);
+            }
+        }
+
+        result
+    }
+
+    fn multiply(&self, a: &Matrix, b: &Matrix) -> Matrix {
+        let n = a.len();
+
+        (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| self.reduce((0..n).map(|k| &a[i][k] * &b[k][j]).sum()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn apply(&self, matrix: &Matrix, counts: &[BigUint]) -> Vec<BigUint> {
+        matrix
+            .iter()
+            .map(|row| self.reduce(row.iter().zip(counts).map(|(m, c)| m * c).sum()))
+            .collect()
+    }
+
+    fn reduce(&self, n: BigUint) -> BigUint {
+        match &self.modulus {
+            Some(modulus) => n % modulus,
+            None => n,
+        }
+    }
+}
+
+fn identity(n: usize) -> Matrix {
+    (0..n)
+        .map(|i| (0..n).map(|j| BigUint::from((i == j) as u32)).collect())
+        .collect()
+}
+
+#[aoc_generator(day6)]
+pub fn input_generator(input: &str) -> Result<Vec<usize>, PopulationError> {
+    input
+        .split(',')
+        .map(|n| n.trim().parse().map_err(|_| PopulationError::Parse))
+        .collect()
+}
+
+#[aoc(day6, part1)]
+pub fn part1(timers: &[usize]) -> Result<BigUint, PopulationError> {
+    let model = PopulationModel::new(RESET_TIMER, NEWBORN_TIMER);
+
+    model.total(&model.census(timers)?, 80)
+}
+
+#[aoc(day6, part2)]
+pub fn part2(timers: &[usize]) -> Result<BigUint, PopulationError> {
+    let model = PopulationModel::new(RESET_TIMER, NEWBORN_TIMER);
+
+    model.total(&model.census(timers)?, 256)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn big(ns: &[u64]) -> Vec<BigUint> {
+        ns.iter().map(|&n| BigUint::from(n)).collect()
+    }
+
     #[test]
     fn input() {
-        let input = input_generator("1,2,3,3");
+        let input = input_generator("1,2,3,3").unwrap();
+        let model = PopulationModel::new(RESET_TIMER, NEWBORN_TIMER);
 
-        assert_eq!(input, [0, 1, 1, 2, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            model.census(&input).unwrap(),
+            big(&[0, 1, 1, 2, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(model.census(&[9]), Err(PopulationError::TimerOutOfRange(9)));
     }
 
     #[test]
     fn simulate_is_correct() {
-        let mut fish_counts = [1, 1, 2, 3, 0, 0, 0, 0, 0];
+        let mut fish_counts = big(&[1, 1, 2, 3, 0, 0, 0, 0, 0]);
+
+        PopulationModel::new(RESET_TIMER, NEWBORN_TIMER)
+            .step(&mut fish_counts)
+            .unwrap();
+
+        assert_eq!(fish_counts, big(&[1, 2, 3, 0, 0, 0, 1, 0, 1]));
+    }
 
-        simulate(&mut fish_counts);
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day6.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input).unwrap(), BigUint::from(358214u64));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day6.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input).unwrap(), BigUint::from(1622533344325u64));
+    }
+
+    #[test]
+    fn checkpoints_match_stepping_test() {
+        // a made up species with a shorter cycle, where newborns mature faster than adults reset
+        let model = PopulationModel::new(4, 2);
+        let initial = model.census(&[0, 1, 4, 4]).unwrap();
+        let checkpoints = model.populations(&initial, &[17, 3, 0, 40]).unwrap();
+        let mut counts = initial.clone();
+
+        assert_eq!(
+            checkpoints.iter().map(|(day, _)| *day).collect::<Vec<_>>(),
+            vec![0, 3, 17, 40]
+        );
+
+        for day in 0..=40 {
+            if let Some((_, expected)) = checkpoints.iter().find(|(d, _)| *d == day) {
+                assert_eq!(&counts, expected);
+            }
+
+            model.step(&mut counts).unwrap();
+        }
+    }
+
+    #[test]
+    fn huge_horizon_test() {
+        let modulus = BigUint::from(1_000_000_007u64);
+        let model = PopulationModel::new(RESET_TIMER, NEWBORN_TIMER).with_modulus(modulus.clone());
+        let exact = PopulationModel::new(RESET_TIMER, NEWBORN_TIMER);
+        let initial = model.census(&[3, 4, 3, 1, 2]).unwrap();
+
+        assert_eq!(
+            model.total(&initial, 256).unwrap(),
+            exact.total(&initial, 256).unwrap() % &modulus
+        );
+        assert!(model.total(&initial, 5_000_000_000).unwrap() < modulus);
+    }
+
+    #[test]
+    fn counts_length_test() {
+        let model = PopulationModel::new(RESET_TIMER, NEWBORN_TIMER);
+        let short = PopulationModel::new(4, 2).census(&[0, 1]).unwrap();
+        let error = PopulationError::CountsLength {
+            expected: 9,
+            found: 5,
+        };
 
-        assert_eq!(fish_counts, [1, 2, 3, 0, 0, 0, 1, 0, 1]);
+        assert_eq!(model.total(&short, 10), Err(error));
+        assert!(model.populations(&big(&[1; 10]), &[1]).is_err());
+        assert!(model.step(&mut big(&[1; 5])).is_err());
     }
 }
@@ -1,42 +1,189 @@
+use std::cmp::Ordering;
+use std::num::ParseIntError;
+
+// how much fuel a crab burns to move a given distance, all of these are convex
+#[derive(Debug, Clone, Copy)]
+pub enum Cost {
+    Linear,
+    Triangular,
+    Quadratic,
+    Custom(fn(i64) -> i64), // must be convex, or the search can settle on a local minimum
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Alignment {
+    pub position: i64,
+    pub cost: i64,
+}
+
+impl Cost {
+    pub fn fuel(&self, d: i64) -> i64 {
+        match self {
+            Cost::Linear => d,
+            // use arithmetic series
+            // https://mathworld.wolfram.com/ArithmeticSeries.html
+            Cost::Triangular => d * (1 + d) / 2,
+            Cost::Quadratic => d * d,
+            Cost::Custom(f) => f(d),
+        }
+    }
+}
+
 #[aoc_generator(day7)]
-pub fn input_generator(input: &str) -> Vec<i32> {
-    input.split(",").map(|s| s.parse().unwrap()).collect()
+pub fn input_generator(input: &str) -> Result<Vec<i64>, ParseIntError> {
+    input.split(',').map(|s| s.trim().parse()).collect()
 }
 
 #[aoc(day7, part1)]
-pub fn part1(positions: &[i32]) -> i32 {
-    let min = positions.iter().min().unwrap();
-    let max = positions.iter().max().unwrap();
-
-    (*min..*max)
-        .map(|i| positions.iter().map(|p| (i - p).abs()).sum())
-        .min()
-        .unwrap()
+pub fn part1(positions: &[i64]) -> Option<i64> {
+    align(positions, Cost::Linear).map(|alignment| alignment.cost)
 }
 
 #[aoc(day7, part2)]
-pub fn part2(positions: &[i32]) -> i32 {
-    let min = positions.iter().min().unwrap();
-    let max = positions.iter().max().unwrap();
+pub fn part2(positions: &[i64]) -> Option<i64> {
+    align(positions, Cost::Triangular).map(|alignment| alignment.cost)
+}
 
-    (*min..*max)
-        .map(|i| positions.iter().map(|p| fuel((i - p).abs())).sum())
-        .min()
-        .unwrap()
+/**
+ * Finds the position that costs the least fuel for every crab to move to,
+ * preferring the leftmost one on ties.
+ *
+ * The built in costs have known optimums: the median for linear costs, the
+ * mean for quadratic ones, and within half a step of the mean for triangular
+ * ones. Anything else is ternary searched between the outermost crabs, which
+ * works because a sum of convex costs is itself convex.
+ */
+pub fn align(positions: &[i64], cost: Cost) -> Option<Alignment> {
+    let min = *positions.iter().min()?;
+    let max = *positions.iter().max()?;
+    let total = |position: i64| -> i64 {
+        positions
+            .iter()
+            .map(|p| cost.fuel((position - p).abs()))
+            .sum()
+    };
+
+    let (lo, hi) = match cost {
+        Cost::Linear => {
+            let mut sorted = positions.to_vec();
+            sorted.sort_unstable();
+
+            // with an even count anything between the two middle crabs is optimal, take the left one
+            let median = sorted[(sorted.len() - 1) / 2];
+
+            (median, median)
+        }
+        Cost::Quadratic | Cost::Triangular => {
+            let n = positions.len() as i64;
+            let sum: i64 = positions.iter().sum();
+
+            (sum.div_euclid(n) - 1, sum.div_euclid(n) + 2)
+        }
+        Cost::Custom(_) => ternary_search(min, max, &total),
+    };
+
+    (lo.max(min)..=hi.min(max))
+        .map(|position| Alignment {
+            position,
+            cost: total(position),
+        })
+        .min_by_key(|alignment| (alignment.cost, alignment.position))
 }
 
-fn fuel(d: i32) -> i32 {
-    // use arithmetic series
-    // https://mathworld.wolfram.com/ArithmeticSeries.html
-    d * (1 + d) / 2
+// narrows [lo, hi] down to a handful of positions around the minimum of convex `f`
+fn ternary_search(mut lo: i64, mut hi: i64, f: &impl Fn(i64) -> i64) -> (i64, i64) {
+    while hi - lo > 2 {
+        let m1 = lo + (hi - lo) / 3;
+        let m2 = hi - (hi - lo) / 3;
+
+        match f(m1).cmp(&f(m2)) {
+            Ordering::Less => hi = m2 - 1,
+            Ordering::Greater => lo = m1 + 1,
+            // flat in between, keep the leftmost side of the plateau in range
+            Ordering::Equal => hi = m2,
+        }
+    }
+
+    (lo, hi)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: [i64; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+    // the original scan over every position, used to check the optimizer
+    fn brute_force(positions: &[i64], cost: Cost) -> Alignment {
+        let min = *positions.iter().min().unwrap();
+        let max = *positions.iter().max().unwrap();
+
+        (min..=max)
+            .map(|position| Alignment {
+                position,
+                cost: positions
+                    .iter()
+                    .map(|p| cost.fuel((position - p).abs()))
+                    .sum(),
+            })
+            .min_by_key(|alignment| (alignment.cost, alignment.position))
+            .unwrap()
+    }
+
     #[test]
     fn fuel_test() {
-        assert_eq!(fuel(4), 10);
+        assert_eq!(Cost::Triangular.fuel(4), 10);
+    }
+
+    #[test]
+    fn example_test() {
+        assert_eq!(
+            align(&EXAMPLE, Cost::Linear),
+            Some(Alignment {
+                position: 2,
+                cost: 37
+            })
+        );
+        assert_eq!(
+            align(&EXAMPLE, Cost::Triangular),
+            Some(Alignment {
+                position: 5,
+                cost: 168
+            })
+        );
+        assert_eq!(align(&[], Cost::Linear), None);
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day7.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input), Some(344138));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day7.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input), Some(94862124));
+    }
+
+    #[test]
+    fn cost_functions_test() {
+        let positions = [3, -8, 40, 41, 41, 12, 0, 7, 99, 5, 5, 100, -20];
+        let costs = [
+            Cost::Linear,
+            Cost::Triangular,
+            Cost::Quadratic,
+            Cost::Custom(|d| d * d * d),
+            Cost::Custom(|d| (d - 10).max(0)),
+        ];
+
+        for cost in costs {
+            assert_eq!(align(&positions, cost), Some(brute_force(&positions, cost)));
+            assert_eq!(align(&EXAMPLE, cost), Some(brute_force(&EXAMPLE, cost)));
+        }
     }
 }
@@ -1,19 +1,36 @@
 use std::fmt;
 use std::{error::Error, str::FromStr};
+
+// the standard display, digit -> lit segments
+//
+//    aaaa
+//   b    c
+//   b    c
+//    dddd
+//   e    f
+//   e    f
+//    gggg
+const SEVEN_SEGMENT: [(usize, &str); 10] = [
+    (0, "abcefg"),
+    (1, "cf"),
+    (2, "acdeg"),
+    (3, "acdfg"),
+    (4, "bcdf"),
+    (5, "abdfg"),
+    (6, "abdefg"),
+    (7, "acf"),
+    (8, "abcdefg"),
+    (9, "abcdfg"),
+];
+
 #[derive(Debug, Clone, Default)]
-struct Entry {
-    signals: Vec<Signal>,
+pub struct Entry {
+    signals: Vec<String>,
     outputs: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
-struct Signal {
-    string: String,
-    number: usize,
-}
-
 #[derive(Debug)]
-struct EntryParseError;
+pub struct EntryParseError;
 impl Error for EntryParseError {}
 
 impl fmt::Display for EntryParseError {
@@ -26,25 +43,169 @@ impl FromStr for Entry {
     type Err = EntryParseError;
 
     fn from_str(s: &str) -> Result<Entry, Self::Err> {
-        let (signals, outputs) = s.split_once(" | ").unwrap();
+        let (signals, outputs) = s.split_once(" | ").ok_or(EntryParseError)?;
 
         Ok(Entry {
-            signals: signals
-                .split(" ")
-                .map(|s| Signal {
-                    string: s.to_string(),
-                    number: signal_to_integer(s),
-                })
-                .collect(),
-            outputs: outputs.split(" ").map(|s| s.to_string()).collect(),
-            ..Entry::default()
+            signals: signals.split(' ').map(|s| s.to_string()).collect(),
+            outputs: outputs.split(' ').map(|s| s.to_string()).collect(),
         })
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum SpecError {
+    InvalidRow(String),
+    DuplicatePattern(usize, usize),
+    TooManySegments,
+}
+
+impl Error for SpecError {}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpecError::InvalidRow(row) => write!(f, "Invalid display spec row: {:?}", row),
+            SpecError::DuplicatePattern(a, b) => {
+                write!(f, "Digits {} and {} light the same segments", a, b)
+            }
+            SpecError::TooManySegments => write!(f, "Display spec has more than 64 segments"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    UnknownWire(char),
+    // no wire permutation explains every signal, lists the signals that are to blame
+    NoMapping(Vec<String>),
+    // more than one wire permutation explains every signal
+    Ambiguous,
+    UnknownOutput(String),
+}
+
+impl Error for DecodeError {}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnknownWire(c) => write!(f, "Unknown wire {:?}", c),
+            DecodeError::NoMapping(signals) => write!(
+                f,
+                "No consistent wire mapping, inconsistent signals: {}",
+                signals.join(" ")
+            ),
+            DecodeError::Ambiguous => write!(f, "More than one wire mapping fits the signals"),
+            DecodeError::UnknownOutput(output) => {
+                write!(f, "Output {:?} does not decode to a digit", output)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EntryError {
+    pub entry: usize, // index of the offending entry
+    pub error: DecodeError,
+}
+
+impl Error for EntryError {}
+
+impl fmt::Display for EntryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Entry {}: {}", self.entry, self.error)
+    }
+}
+
+// a display layout, every digit is a bitmask over the spec's segment names
+#[derive(Debug)]
+pub struct DisplaySpec {
+    segments: Vec<char>,
+    digits: Vec<(usize, u64)>,
+}
+
+// `mapping[wire]` is the index of the segment that wire is connected to
+type Mapping = Vec<usize>;
+
+impl DisplaySpec {
+    pub fn new(table: &[(usize, &str)]) -> Result<DisplaySpec, SpecError> {
+        let mut segments: Vec<char> = table.iter().flat_map(|(_, s)| s.chars()).collect();
+
+        segments.sort_unstable();
+        segments.dedup();
+
+        if segments.len() > 64 {
+            return Err(SpecError::TooManySegments);
+        }
+
+        let mut spec = DisplaySpec {
+            segments,
+            digits: Vec::with_capacity(table.len()),
+        };
+
+        for &(digit, pattern) in table {
+            // every char is in the alphabet we just built, so this can't fail
+            let mask = spec.mask(pattern).unwrap_or(0);
+
+            if let Some(&(other, _)) = spec.digits.iter().find(|(_, m)| *m == mask) {
+                return Err(SpecError::DuplicatePattern(other, digit));
+            }
+
+            spec.digits.push((digit, mask));
+        }
+
+        Ok(spec)
+    }
+
+    pub fn seven_segment() -> DisplaySpec {
+        DisplaySpec::new(&SEVEN_SEGMENT).expect("the standard display is valid")
+    }
+
+    fn mask(&self, pattern: &str) -> Result<u64, DecodeError> {
+        pattern.chars().try_fold(0, |mask, c| {
+            let i = self
+                .segments
+                .iter()
+                .position(|&s| s == c)
+                .ok_or(DecodeError::UnknownWire(c))?;
+
+            Ok(mask | 1 << i)
+        })
+    }
+
+    fn digit(&self, mask: u64) -> Option<usize> {
+        self.digits
+            .iter()
+            .find(|&&(_, m)| m == mask)
+            .map(|&(digit, _)| digit)
+    }
+}
+
+// one row per digit, like "7: acf"
+impl FromStr for DisplaySpec {
+    type Err = SpecError;
+
+    fn from_str(s: &str) -> Result<DisplaySpec, Self::Err> {
+        let table = s
+            .lines()
+            .map(|row| {
+                let (digit, pattern) = row
+                    .split_once(": ")
+                    .ok_or_else(|| SpecError::InvalidRow(row.to_string()))?;
+                let digit = digit
+                    .parse()
+                    .map_err(|_| SpecError::InvalidRow(row.to_string()))?;
+
+                Ok((digit, pattern))
+            })
+            .collect::<Result<Vec<_>, SpecError>>()?;
+
+        DisplaySpec::new(&table)
+    }
+}
+
 #[aoc_generator(day8)]
-fn input_generator(input: &str) -> Vec<Entry> {
-    input.split("\n").map(|l| l.parse().unwrap()).collect()
+fn input_generator(input: &str) -> Result<Vec<Entry>, EntryParseError> {
+    input.lines().map(|l| l.parse()).collect()
 }
 
 #[aoc(day8, part1)]
@@ -61,89 +222,267 @@ fn part1(lines: &[Entry]) -> usize {
 }
 
 #[aoc(day8, part2)]
-fn part2(lines: &[Entry]) -> usize {
-    lines.iter().map(decode_line).sum()
+fn part2(lines: &[Entry]) -> Result<usize, EntryError> {
+    let spec = DisplaySpec::seven_segment();
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(entry, line)| decode_line(&spec, line).map_err(|error| EntryError { entry, error }))
+        .sum()
 }
 
-fn decode_line(entry: &Entry) -> usize {
-    let mut translation: [usize; 10] = [0; 10];
+// every entry that can't be decoded with `spec`, and why
+pub fn find_inconsistent(spec: &DisplaySpec, lines: &[Entry]) -> Vec<EntryError> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(entry, line)| {
+            decode_line(spec, line)
+                .err()
+                .map(|error| EntryError { entry, error })
+        })
+        .collect()
+}
 
-    translation[1] = find(&entry.signals, by_length(2));
-    translation[4] = find(&entry.signals, by_length(4));
-    translation[7] = find(&entry.signals, by_length(3));
-    translation[8] = find(&entry.signals, by_length(7));
+fn decode_line(spec: &DisplaySpec, entry: &Entry) -> Result<usize, DecodeError> {
+    let signals = entry
+        .signals
+        .iter()
+        .map(|s| spec.mask(s))
+        .collect::<Result<Vec<u64>, DecodeError>>()?;
 
-    for signal in entry.signals.iter().filter(by_length(6)) {
-        if overlap(signal, translation[4]) == 4 {
-            translation[9] = signal.number;
-        } else if overlap(signal, translation[1]) == 2 {
-            translation[0] = signal.number;
-        } else {
-            translation[6] = signal.number;
-        }
-    }
+    let mapping = match solve(spec, &signals).as_slice() {
+        [mapping] => mapping.clone(),
+        [] => return Err(DecodeError::NoMapping(blame(spec, entry, &signals))),
+        _ => return Err(DecodeError::Ambiguous),
+    };
 
-    for signal in entry.signals.iter().filter(by_length(5)) {
-        if overlap(signal, translation[7]) == 3 {
-            translation[3] = signal.number;
-        } else if overlap(signal, translation[6]) == 5 {
-            translation[5] = signal.number;
-        } else {
-            translation[2] = signal.number;
-        }
-    }
+    entry.outputs.iter().try_fold(0, |value, output| {
+        let digit = spec
+            .digit(remap(spec.mask(output)?, &mapping))
+            .ok_or_else(|| DecodeError::UnknownOutput(output.to_string()))?;
 
-    entry
-        .outputs
-        .iter()
-        .map(|o| match_signal(o, translation))
-        .map(|n| n.to_string())
-        .collect::<Vec<String>>()
-        .join("")
-        .parse::<usize>()
-        .unwrap()
+        Ok(value * 10 + digit)
+    })
 }
 
 /**
- * Takes a signal (like "ebdcfa") and returns an integer value by converting the
- * characters into their ASCII values and summing them.
+ * Finds up to two wire permutations that turn every signal into a distinct
+ * digit of the spec. Each signal is tried against every unused digit with the
+ * same number of segments, narrowing down the segments each wire could be
+ * connected to as it goes.
  */
-fn signal_to_integer(signal: &str) -> usize {
-    signal.chars().map(|c| 1 << (c as usize - 97)).sum()
+fn solve(spec: &DisplaySpec, signals: &[u64]) -> Vec<Mapping> {
+    let all = ((1u128 << spec.segments.len()) - 1) as u64;
+    let candidates = vec![all; spec.segments.len()];
+    let mut mappings = Vec::new();
+
+    assign_signals(
+        spec,
+        signals,
+        &mut vec![false; spec.digits.len()],
+        &candidates,
+        &mut mappings,
+    );
+
+    mappings
 }
 
-fn overlap(signal: &Signal, translation: usize) -> u32 {
-    (signal.number & translation).count_ones()
+fn assign_signals(
+    spec: &DisplaySpec,
+    signals: &[u64],
+    used_digits: &mut [bool],
+    candidates: &[u64],
+    mappings: &mut Vec<Mapping>,
+) {
+    let (signal, rest) = match signals.split_first() {
+        Some(split) => split,
+        None => return assign_wires(candidates, 0, 0, &mut Vec::new(), mappings),
+    };
+
+    for (i, &(_, digit)) in spec.digits.iter().enumerate() {
+        if used_digits[i] || digit.count_ones() != signal.count_ones() {
+            continue;
+        }
+
+        // wires in the signal must light a segment of the digit, the others must not
+        let narrowed: Vec<u64> = candidates
+            .iter()
+            .enumerate()
+            .map(|(wire, &c)| {
+                if signal & 1 << wire != 0 {
+                    c & digit
+                } else {
+                    c & !digit
+                }
+            })
+            .collect();
+
+        if narrowed.iter().all(|&c| c != 0) {
+            used_digits[i] = true;
+            assign_signals(spec, rest, used_digits, &narrowed, mappings);
+            used_digits[i] = false;
+        }
+
+        if mappings.len() > 1 {
+            return;
+        }
+    }
 }
 
-fn find<T>(ns: &Vec<Signal>, func: T) -> usize
-where
-    T: Fn(&&Signal) -> bool,
-{
-    for (i, n) in ns.iter().enumerate() {
-        if func(&n) {
-            return signal_to_integer(&ns[i].string);
+// turn the remaining candidates for each wire into distinct segments
+fn assign_wires(
+    candidates: &[u64],
+    wire: usize,
+    used_segments: u64,
+    mapping: &mut Mapping,
+    mappings: &mut Vec<Mapping>,
+) {
+    if wire == candidates.len() {
+        mappings.push(mapping.clone());
+        return;
+    }
+
+    for segment in 0..candidates.len() {
+        if candidates[wire] & 1 << segment == 0 || used_segments & 1 << segment != 0 {
+            continue;
+        }
+
+        mapping.push(segment);
+        assign_wires(
+            candidates,
+            wire + 1,
+            used_segments | 1 << segment,
+            mapping,
+            mappings,
+        );
+        mapping.pop();
+
+        if mappings.len() > 1 {
+            return;
         }
     }
+}
 
-    panic!("Could not find!")
+// the signals that, once dropped, leave a solvable entry (or all of them if no single one does)
+fn blame(spec: &DisplaySpec, entry: &Entry, signals: &[u64]) -> Vec<String> {
+    let culprits: Vec<String> = (0..signals.len())
+        .filter(|&i| {
+            let rest: Vec<u64> = [&signals[..i], &signals[i + 1..]].concat();
+            !solve(spec, &rest).is_empty()
+        })
+        .map(|i| entry.signals[i].clone())
+        .collect();
+
+    if culprits.is_empty() {
+        entry.signals.clone()
+    } else {
+        culprits
+    }
 }
 
-// higher-order functions, neeeat
-fn by_length(len: usize) -> impl Fn(&&Signal) -> bool {
-    move |s: &&Signal| s.string.chars().count() == len
+fn remap(signal: u64, mapping: &Mapping) -> u64 {
+    mapping
+        .iter()
+        .enumerate()
+        .filter(|&(wire, _)| signal & 1 << wire != 0)
+        .fold(0, |mask, (_, &segment)| mask | 1 << segment)
 }
 
-/**
- * Matches a signal pattern to a decoded digit. (The sequence of signals might
- * differ but the computed integer value will always be equal).
- */
-fn match_signal(signal: &String, translation: [usize; 10]) -> usize {
-    for (i, n) in translation.iter().enumerate() {
-        if signal_to_integer(signal) == *n {
-            return i;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str =
+        "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf";
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day8.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input), 456);
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day8.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input).unwrap(), 1091609);
+    }
+
+    #[test]
+    fn decode_line_test() {
+        let entry: Entry = EXAMPLE.parse().unwrap();
+
+        assert_eq!(decode_line(&DisplaySpec::seven_segment(), &entry), Ok(5353));
+    }
+
+    #[test]
+    fn custom_spec_test() {
+        let spec: DisplaySpec = "0: a\n1: ab\n2: bc\n3: abc".parse().unwrap();
+        let entry: Entry = "c bc ab abc | c ab bc".parse().unwrap();
+
+        assert_eq!(decode_line(&spec, &entry), Ok(21));
+        assert_eq!(
+            "0: ab\n1: ba".parse::<DisplaySpec>().unwrap_err(),
+            SpecError::DuplicatePattern(0, 1)
+        );
     }
 
-    panic!("Could not match signal")
+    #[test]
+    fn many_digits_test() {
+        // 64 three-segment digits, then a lone "a" as digit 64
+        let patterns: Vec<String> = (0u32..1024)
+            .filter(|n| n.count_ones() == 3)
+            .take(64)
+            .map(|n| {
+                ('a'..='j')
+                    .filter(|c| n & 1 << (*c as u32 - 'a' as u32) != 0)
+                    .collect()
+            })
+            .chain(std::iter::once("a".to_string()))
+            .collect();
+        let table: Vec<(usize, &str)> = patterns.iter().map(|p| p.as_str()).enumerate().collect();
+        let spec = DisplaySpec::new(&table).unwrap();
+        let entry: Entry = "a | a".parse().unwrap();
+
+        // the other nine wires could go anywhere
+        assert_eq!(decode_line(&spec, &entry), Err(DecodeError::Ambiguous));
+    }
+
+    #[test]
+    fn inconsistent_entries_test() {
+        let spec = DisplaySpec::seven_segment();
+        let lines = input_generator(&[
+            EXAMPLE,
+            // "ab" swapped for a second 4-segment signal
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb abcd | cdfeb fcadb cdfeb cdbaf",
+            // a lone 8 lights every wire, so any permutation fits
+            "acedgfb | acedgfb",
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb xyz",
+        ]
+        .join("\n"))
+        .unwrap();
+
+        assert_eq!(
+            find_inconsistent(&spec, &lines),
+            vec![
+                EntryError {
+                    entry: 1,
+                    error: DecodeError::NoMapping(vec!["abcd".to_string()])
+                },
+                EntryError {
+                    entry: 2,
+                    error: DecodeError::Ambiguous
+                },
+                EntryError {
+                    entry: 3,
+                    error: DecodeError::UnknownWire('x')
+                },
+            ]
+        );
+    }
 }
@@ -1,109 +1,267 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 
-const X: i8 = 100;
-const Y: i8 = 100;
+// the puzzle's basins are bounded by locations of height 9
+const WALL_HEIGHT: u8 = 9;
 
-type HeightMap = [[i8; X as usize]; Y as usize];
-type Point = (i8, i8);
+type Point = (usize, usize); // x, y
 
-// x, y
-const OFFSETS: [Point; 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+#[derive(Debug)]
+struct ParseError;
+
+impl Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error parsing height map")
+    }
+}
+
+#[derive(Debug)]
+pub struct HeightMap {
+    width: usize,
+    height: usize,
+    heights: Vec<u8>, // row-major, `width * height` long
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Basin {
+    pub low_point: Point, // lowest location in the basin, the first one in reading order on ties
+    pub size: usize,      // number of locations in the basin
+    pub cells: Vec<Point>, // every location in the basin, in reading order
+}
+
+// disjoint-set forest over cell indices, with path halving and union by size
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        UnionFind {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+
+        if a == b {
+            return;
+        }
+
+        let (big, small) = if self.sizes[a] >= self.sizes[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+
+        self.parents[small] = big;
+        self.sizes[big] += self.sizes[small];
+    }
+}
+
+impl HeightMap {
+    pub fn get(&self, (x, y): Point) -> u8 {
+        self.heights[y * self.width + x]
+    }
+
+    fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+    }
+
+    fn neighbors(&self, (x, y): Point) -> impl Iterator<Item = Point> + '_ {
+        let left = x.checked_sub(1).map(|x| (x, y));
+        let up = y.checked_sub(1).map(|y| (x, y));
+        let right = Some((x + 1, y)).filter(|&(x, _)| x < self.width);
+        let down = Some((x, y + 1)).filter(|&(_, y)| y < self.height);
+
+        [left, right, up, down].into_iter().flatten()
+    }
+}
 
 #[aoc_generator(day9)]
-pub fn input_generator(input: &str) -> HeightMap {
-    let mut result = [[0; X as usize]; Y as usize];
+pub fn input_generator(input: &str) -> Result<HeightMap, Box<dyn Error>> {
+    let mut heights = Vec::with_capacity(input.len());
+    let mut height = 0;
+    let width = input.lines().next().map_or(0, |line| line.len());
+
+    for line in input.lines() {
+        if line.len() != width {
+            return Err(Box::new(ParseError));
+        }
 
-    for (y, line) in input.split("\n").enumerate() {
-        for (x, n) in line.bytes().enumerate() {
-            result[y][x] = (n - 48) as i8;
+        for n in line.chars() {
+            heights.push(n.to_digit(10).ok_or(ParseError)? as u8);
         }
+
+        height += 1;
     }
 
-    result
+    Ok(HeightMap {
+        width,
+        height,
+        heights,
+    })
 }
 
 #[aoc(day9, part1)]
-pub fn part1(map: &HeightMap) -> isize {
-    points_iter()
-        .filter(|point| is_local_minimum(point, &map))
-        .map(|point| risk_level(&point, &map))
+pub fn part1(map: &HeightMap) -> usize {
+    map.points()
+        .filter(|&point| is_local_minimum(point, map))
+        .map(|point| risk_level(point, map))
         .sum()
 }
 
 #[aoc(day9, part2)]
 pub fn part2(map: &HeightMap) -> usize {
-    let mut basin_sizes: Vec<usize> = points_iter()
-        .filter(|point| is_local_minimum(point, &map))
-        .map(|point| find_basin(&point, &map))
-        .map(|basin| basin.len())
-        .collect::<Vec<usize>>();
+    let mut basin_sizes: Vec<usize> = label_basins(map, WALL_HEIGHT)
+        .iter()
+        .map(|basin| basin.size)
+        .collect();
 
-    basin_sizes.sort();
-    basin_sizes.reverse();
+    basin_sizes.sort_unstable_by(|a, b| b.cmp(a));
 
-    basin_sizes[..3].iter().product()
+    basin_sizes.iter().take(3).product()
 }
 
-fn find_basin(origin: &Point, map: &HeightMap) -> Vec<Point> {
-    let mut visited: HashSet<Point> = HashSet::new();
-    let mut candidates = valid_neighbors(origin);
-
-    visited.insert(*origin);
+/**
+ * Labels every connected region of locations below `wall` height in a single
+ * pass over the map. Each location only has to be joined with the location to
+ * its left and the one above it, everything else gets picked up transitively.
+ */
+pub fn label_basins(map: &HeightMap, wall: u8) -> Vec<Basin> {
+    let mut sets = UnionFind::new(map.heights.len());
 
-    while candidates.len() > 0 {
-        // grab first candidate as center point
-        let point = candidates.swap_remove(0);
+    for (x, y) in map.points() {
+        let i = y * map.width + x;
 
-        // mark this candidate as visited
-        visited.insert(point);
+        if map.heights[i] >= wall {
+            continue;
+        }
 
-        // iterate over the neighbors, filtering out those that are height 9
-        for (x, y) in valid_neighbors(&point) {
-            if visited.contains(&(x, y)) || map[y as usize][x as usize] == 9 {
-                continue;
-            }
+        if x > 0 && map.heights[i - 1] < wall {
+            sets.union(i, i - 1);
+        }
 
-            // add each neighbor as a new candidate to traverse, make
-            // sure we don't add the same candidate more than once
-            visited.insert((x, y));
-            candidates.push((x, y));
+        if y > 0 && map.heights[i - map.width] < wall {
+            sets.union(i, i - map.width);
         }
     }
 
-    visited.into_iter().collect()
-}
+    // group cells by their root, keeping basins in the order they are first seen
+    let mut basins: Vec<Basin> = Vec::new();
+    let mut labels: HashMap<usize, usize> = HashMap::new();
 
-fn points_iter() -> impl Iterator<Item = Point> {
-    (0..Y).flat_map(|y| (0..X).map(move |x| (x, y)))
-}
+    for point in map.points() {
+        let i = point.1 * map.width + point.0;
+
+        if map.heights[i] >= wall {
+            continue;
+        }
 
-fn is_local_minimum(point: &Point, map: &HeightMap) -> bool {
-    let (x, y) = point;
-    let height = map[*y as usize][*x as usize];
+        let label = *labels.entry(sets.find(i)).or_insert_with(|| {
+            basins.push(Basin {
+                low_point: point,
+                size: 0,
+                cells: Vec::new(),
+            });
 
-    for (x, y) in valid_neighbors(point) {
-        if map[y as usize][x as usize] <= height {
-            return false;
+            basins.len() - 1
+        });
+
+        let basin = &mut basins[label];
+
+        if map.get(point) < map.get(basin.low_point) {
+            basin.low_point = point;
         }
+
+        basin.size += 1;
+        basin.cells.push(point);
     }
 
-    true
+    basins
 }
 
-fn risk_level(point: &Point, map: &HeightMap) -> isize {
-    (map[point.1 as usize][point.0 as usize] + 1) as isize
-}
+fn is_local_minimum(point: Point, map: &HeightMap) -> bool {
+    let height = map.get(point);
 
-fn valid_neighbors(origin: &Point) -> Vec<Point> {
-    let (x, y) = origin;
+    map.neighbors(point)
+        .all(|neighbor| map.get(neighbor) > height)
+}
 
-    OFFSETS
-        .iter()
-        .map(|(a, b)| (x + a, y + b))
-        .filter(|(x, y)| valid_point(*x, *y))
-        .collect()
+fn risk_level(point: Point, map: &HeightMap) -> usize {
+    map.get(point) as usize + 1
 }
 
-fn valid_point(x: i8, y: i8) -> bool {
-    x >= 0 && x < X && y >= 0 && y < Y
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "2199943210\n3987894921\n9856789892\n8767896789\n9899965678";
+
+    #[test]
+    fn example_test() {
+        let input = input_generator(EXAMPLE).unwrap();
+
+        assert_eq!(part1(&input), 15);
+        assert_eq!(part2(&input), 1134);
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2021/day9.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part1(&input), 580);
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2021/day9.txt");
+        let input = input_generator(input_text.trim()).unwrap();
+
+        assert_eq!(part2(&input), 856716);
+    }
+
+    #[test]
+    fn label_basins_test() {
+        let input = input_generator(EXAMPLE).unwrap();
+        let basins = label_basins(&input, WALL_HEIGHT);
+
+        assert_eq!(basins.len(), 4);
+        assert_eq!(basins[0].low_point, (1, 0));
+        assert_eq!(basins[0].cells, vec![(0, 0), (1, 0), (0, 1)]);
+        assert_eq!(basins[1].low_point, (9, 0));
+        assert_eq!(basins[1].size, 9);
+
+        // with a lower wall the top left basin shrinks to just its low point
+        let basins = label_basins(&input, 2);
+
+        assert_eq!(basins[0].cells, vec![(1, 0)]);
+    }
+
+    #[test]
+    fn large_map_test() {
+        // far too wide for the old i8 coordinates, split down the middle by a wall
+        let row = format!("{}9{}", "1".repeat(300), "2".repeat(300));
+        let input = input_generator(&vec![row; 200].join("\n")).unwrap();
+        let basins = label_basins(&input, WALL_HEIGHT);
+
+        assert_eq!(basins.len(), 2);
+        assert_eq!(basins[0].size, 60_000);
+        assert_eq!(basins[1].low_point, (301, 0));
+    }
 }
@@ -47,31 +47,111 @@ In the example above, the top three Elves are the fourth Elf (with 24000 Calorie
 Find the top three Elves carrying the most Calories. How many Calories are those Elves carrying in total?
 */
 
-#[aoc_generator(day1)]
-pub fn input_generator(input: &str) -> Vec<Vec<usize>> {
-    input
-        .split("\n\n")
-        .map(|line| line.split("\n").map(|x| x.parse().unwrap()).collect())
-        .collect()
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::num::ParseIntError;
+
+#[derive(Debug)]
+pub enum CalorieError {
+    Io(io::Error),
+    Parse { line: usize, error: ParseIntError }, // 1-based line number
 }
 
-#[aoc(day1, part1)]
-pub fn part1(elves: &[Vec<usize>]) -> usize {
-    let mut max = 0;
+impl Error for CalorieError {}
 
-    for elf in elves {
-        max = max.max(elf.iter().sum());
+impl fmt::Display for CalorieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalorieError::Io(error) => write!(f, "Error reading inventory: {}", error),
+            CalorieError::Parse { line, error } => {
+                write!(f, "Error parsing calories on line {}: {}", line, error)
+            }
+        }
     }
+}
 
-    max
+#[derive(Debug, Clone, PartialEq)]
+pub struct Elf {
+    pub index: usize, // 0-based position of the elf's inventory in the list
+    pub items: usize,
+    pub total: usize,
 }
 
-#[aoc(day1, part2)]
-pub fn part2(elves: &[Vec<usize>]) -> usize {
-    let mut sorted_sums: Vec<usize> = elves.iter().map(|elf| elf.iter().sum()).collect();
-    sorted_sums.sort_unstable_by(|a, b| b.cmp(a));
+/**
+ * Reads blank line separated inventories one line at a time, keeping only the
+ * `k` best elves seen so far in a min-heap, so memory stays O(k) however long
+ * the list is. Elves come back ranked by total, ties going to the earlier elf.
+ */
+pub fn top_elves(reader: impl BufRead, k: usize) -> Result<Vec<Elf>, CalorieError> {
+    // the root is always the worst ranked elf, ready to be bumped
+    let mut heap: BinaryHeap<Reverse<(usize, Reverse<usize>, usize)>> =
+        BinaryHeap::with_capacity(k + 1);
+    let mut index = 0;
+    let mut current: Option<(usize, usize)> = None; // items, total
+
+    let mut offer = |index: usize, (items, total): (usize, usize)| {
+        heap.push(Reverse((total, Reverse(index), items)));
+
+        if heap.len() > k {
+            heap.pop();
+        }
+    };
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(CalorieError::Io)?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            // runs of blank lines only separate a single pair of elves
+            if let Some(elf) = current.take() {
+                offer(index, elf);
+                index += 1;
+            }
+
+            continue;
+        }
+
+        let calories: usize = line
+            .parse()
+            .map_err(|error| CalorieError::Parse { line: i + 1, error })?;
+        let (items, total) = current.get_or_insert((0, 0));
+
+        *items += 1;
+        *total += calories;
+    }
+
+    if let Some(elf) = current {
+        offer(index, elf);
+    }
+
+    Ok(heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((total, Reverse(index), items))| Elf {
+            index,
+            items,
+            total,
+        })
+        .collect())
+}
 
-    sorted_sums.iter().take(3).sum()
+#[aoc(day1, part1)]
+pub fn part1(input: &str) -> Result<usize, CalorieError> {
+    Ok(top_elves(input.as_bytes(), 1)?
+        .iter()
+        .map(|elf| elf.total)
+        .sum())
+}
+
+#[aoc(day1, part2)]
+pub fn part2(input: &str) -> Result<usize, CalorieError> {
+    Ok(top_elves(input.as_bytes(), 3)?
+        .iter()
+        .map(|elf| elf.total)
+        .sum())
 }
 
 #[cfg(test)]
@@ -83,13 +163,23 @@ mod tests {
         let input = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000";
 
         assert_eq!(
-            input_generator(input),
+            top_elves(input.as_bytes(), 3).unwrap(),
             [
-                vec![1000, 2000, 3000],
-                vec![4000],
-                vec![5000, 6000],
-                vec![7000, 8000, 9000],
-                vec![10000]
+                Elf {
+                    index: 3,
+                    items: 3,
+                    total: 24000
+                },
+                Elf {
+                    index: 2,
+                    items: 2,
+                    total: 11000
+                },
+                Elf {
+                    index: 4,
+                    items: 1,
+                    total: 10000
+                },
             ]
         );
     }
@@ -97,12 +187,51 @@ mod tests {
     #[test]
     fn sample1() {
         let input = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000";
-        assert_eq!(part1(&input_generator(input)), 24000);
+        assert_eq!(part1(input).unwrap(), 24000);
     }
 
     #[test]
     fn sample2() {
         let input = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000";
-        assert_eq!(part2(&input_generator(input)), 45000);
+        assert_eq!(part2(input).unwrap(), 45000);
+    }
+
+    #[test]
+    fn ranking() {
+        // ties go to the earlier elf, extra blank lines don't make empty elves
+        let input = "5\n\n\n\n2\n3\n\n1\n\n4\n1\n";
+        let ranked = top_elves(input.as_bytes(), 10).unwrap();
+
+        assert_eq!(
+            ranked.iter().map(|elf| elf.index).collect::<Vec<_>>(),
+            [0, 1, 3, 2]
+        );
+        assert_eq!(ranked[1].items, 2);
+        assert!(top_elves(input.as_bytes(), 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_line() {
+        let error = top_elves("1000\n\n20x0\n".as_bytes(), 3).unwrap_err();
+
+        assert!(matches!(error, CalorieError::Parse { line: 3, .. }));
+        assert_eq!(
+            error.to_string(),
+            "Error parsing calories on line 3: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2022/day1.txt");
+
+        assert_eq!(part1(input_text).unwrap(), 73211);
+    }
+
+    #[test]
+    fn part2_test() {
+        let input_text = include_str!("../input/2022/day1.txt");
+
+        assert_eq!(part2(input_text).unwrap(), 213958);
     }
 }
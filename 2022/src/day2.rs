@@ -39,108 +39,352 @@ Now that you're correctly decrypting the ultra top secret strategy guide, you wo
 Following the Elf's instructions for the second column, what would your total score be if everything goes exactly according to your strategy guide?
 */
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-pub enum Move {
-    Boulder = 0,
-    Parchment = 1,
-    Shears = 2,
-}
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 
+// a shape is its index in the game's list of shapes
+pub type Shape = usize;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Outcome {
     Win = 0,
     Draw = 1,
     Loss = 2,
 }
 
-const MOVE_SCORES: [usize; 3] = [1, 2, 3];
+const OUTCOMES: [Outcome; 3] = [Outcome::Win, Outcome::Draw, Outcome::Loss];
 
-#[aoc_generator(day2)]
-pub fn input_generator(input: &str) -> Vec<(Move, Move)> {
-    input
-        .lines()
-        .map(|line| {
-            let parts: Vec<&str> = line.split(' ').collect();
-
-            (
-                decode_move(
-                    parts[0]
-                        .chars()
-                        .next()
-                        .expect("Line didn't contain first move"),
-                ),
-                decode_move(
-                    parts[1]
-                        .chars()
-                        .next()
-                        .expect("Line didn't contain second move"),
-                ),
-            )
-        })
-        .collect()
+// indexed by `Outcome`
+const OUTCOME_SCORES: [usize; 3] = [6, 3, 0];
+
+#[derive(Debug, PartialEq)]
+pub enum GameError {
+    UnknownShape(String),
+    BeatsItself(String),
+    Contradiction(String, String), // both shapes are listed as beating the other
+    EvenCycle(usize),
+    ScoreCount(usize), // number of shape scores given, which doesn't match the number of shapes
+    InvalidRound(usize),
+    UnknownSymbol { round: usize, symbol: char },
+    NoResponse { round: usize, outcome: Outcome }, // nothing in the game gets this outcome
 }
 
-#[aoc(day2, part1)]
-pub fn part1(rounds: &[(Move, Move)]) -> usize {
-    rounds.iter().map(|round| round_score(*round)).sum()
+impl Error for GameError {}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::UnknownShape(name) => write!(f, "Unknown shape {:?}", name),
+            GameError::BeatsItself(name) => write!(f, "{} can't beat itself", name),
+            GameError::Contradiction(a, b) => write!(f, "{} and {} both beat each other", a, b),
+            GameError::EvenCycle(n) => {
+                write!(f, "A cyclic game needs an odd number of shapes, not {}", n)
+            }
+            GameError::ScoreCount(n) => write!(f, "Got {} shape scores, expected one per shape", n),
+            GameError::InvalidRound(round) => write!(f, "Round {} isn't two symbols", round),
+            GameError::UnknownSymbol { round, symbol } => {
+                write!(f, "Unknown symbol {:?} in round {}", symbol, round)
+            }
+            GameError::NoResponse { round, outcome } => {
+                write!(f, "No shape gets a {:?} in round {}", outcome, round)
+            }
+        }
+    }
 }
 
-#[aoc(day2, part2)]
-pub fn part2(rounds: &[(Move, Move)]) -> usize {
-    rounds
-        .iter()
-        .map(|round| round_score((round.0, strategic_move(*round))))
-        .sum()
+/**
+ * A game of hand shapes, defined by a table of which shape beats which. Any
+ * pair not in the table is a draw. Shapes score their `shape_scores` entry
+ * when thrown, on top of the score for the round's outcome.
+ */
+#[derive(Debug, Clone)]
+pub struct Game {
+    names: Vec<String>,
+    beats: Vec<Vec<bool>>, // beats[a][b] when `a` beats `b`
+    shape_scores: Vec<usize>,
+    outcome_scores: [usize; 3],
 }
 
-fn decode_move(symbol: char) -> Move {
-    match symbol {
-        'A' | 'X' => Move::Boulder,
-        'B' | 'Y' => Move::Parchment,
-        'C' | 'Z' => Move::Shears,
-        _ => panic!("Invalid move!"),
+impl Game {
+    // `rules` are (winner, loser) pairs
+    pub fn new(names: &[&str], rules: &[(&str, &str)]) -> Result<Self, GameError> {
+        let n = names.len();
+        let mut game = Game {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            beats: vec![vec![false; n]; n],
+            shape_scores: (1..=n).collect(),
+            outcome_scores: OUTCOME_SCORES,
+        };
+
+        for (winner, loser) in rules {
+            let (a, b) = (game.find(winner)?, game.find(loser)?);
+
+            if a == b {
+                return Err(GameError::BeatsItself(winner.to_string()));
+            }
+
+            if game.beats[b][a] {
+                return Err(GameError::Contradiction(
+                    winner.to_string(),
+                    loser.to_string(),
+                ));
+            }
+
+            game.beats[a][b] = true;
+        }
+
+        Ok(game)
+    }
+
+    // every shape beats the ones an odd number of steps before it, wrapping around
+    #[allow(clippy::manual_is_multiple_of)] // `is_multiple_of` needs Rust 1.87
+    pub fn cyclic(names: &[&str]) -> Result<Self, GameError> {
+        let n = names.len();
+
+        if n % 2 == 0 {
+            return Err(GameError::EvenCycle(n));
+        }
+
+        let rules: Vec<(&str, &str)> = (0..n)
+            .flat_map(|a| {
+                (1..n)
+                    .step_by(2)
+                    .map(move |d| (names[a], names[(a + n - d) % n]))
+            })
+            .collect();
+
+        Game::new(names, &rules)
+    }
+
+    pub fn rock_paper_scissors() -> Self {
+        Game::cyclic(&["Rock", "Paper", "Scissors"]).expect("3 is odd")
+    }
+
+    pub fn rock_paper_scissors_lizard_spock() -> Self {
+        Game::cyclic(&["Rock", "Paper", "Scissors", "Spock", "Lizard"]).expect("5 is odd")
     }
+
+    pub fn with_shape_scores(mut self, scores: &[usize]) -> Result<Self, GameError> {
+        if scores.len() != self.names.len() {
+            return Err(GameError::ScoreCount(scores.len()));
+        }
+
+        self.shape_scores = scores.to_vec();
+        Ok(self)
+    }
+
+    // indexed by `Outcome`
+    pub fn with_outcome_scores(mut self, scores: [usize; 3]) -> Self {
+        self.outcome_scores = scores;
+        self
+    }
+
+    pub fn shapes(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn shape(&self, name: &str) -> Option<Shape> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    pub fn name(&self, shape: Shape) -> &str {
+        &self.names[shape]
+    }
+
+    pub fn outcome(&self, yours: Shape, theirs: Shape) -> Outcome {
+        if self.beats[yours][theirs] {
+            Outcome::Win
+        } else if self.beats[theirs][yours] {
+            Outcome::Loss
+        } else {
+            Outcome::Draw
+        }
+    }
+
+    // the best scoring shape that gets `outcome` against `theirs`
+    pub fn response(&self, theirs: Shape, outcome: Outcome) -> Option<Shape> {
+        (0..self.shapes())
+            .filter(|&yours| self.outcome(yours, theirs) == outcome)
+            .max_by_key(|&yours| (self.shape_scores[yours], Reverse(yours)))
+    }
+
+    pub fn score(&self, yours: Shape, theirs: Shape) -> usize {
+        self.shape_scores[yours] + self.outcome_scores[self.outcome(yours, theirs) as usize]
+    }
+
+    // total score for playing every round as decoded by `encoding`
+    pub fn play(&self, rounds: &[(char, char)], encoding: &Encoding) -> Result<usize, GameError> {
+        rounds
+            .iter()
+            .enumerate()
+            .map(|(i, &(a, b))| {
+                let round = i + 1;
+                let theirs = *encoding
+                    .theirs
+                    .get(&a)
+                    .ok_or(GameError::UnknownSymbol { round, symbol: a })?;
+                let yours = match encoding.yours.get(&b) {
+                    Some(Strategy::Shape(shape)) => *shape,
+                    Some(Strategy::Outcome(outcome)) => {
+                        self.response(theirs, *outcome)
+                            .ok_or(GameError::NoResponse {
+                                round,
+                                outcome: *outcome,
+                            })?
+                    }
+                    None => return Err(GameError::UnknownSymbol { round, symbol: b }),
+                };
+
+                Ok(self.score(yours, theirs))
+            })
+            .sum()
+    }
+
+    fn find(&self, name: &str) -> Result<Shape, GameError> {
+        self.shape(name)
+            .ok_or_else(|| GameError::UnknownShape(name.to_string()))
+    }
+}
+
+// what the second column of the strategy guide tells you to do
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Strategy {
+    Shape(Shape),
+    Outcome(Outcome),
 }
 
-fn round_outcome(your_move: &Move, their_move: &Move) -> Outcome {
-    match (your_move, their_move) {
-        (Move::Boulder, Move::Shears) => Outcome::Win,
-        (Move::Parchment, Move::Boulder) => Outcome::Win,
-        (Move::Shears, Move::Parchment) => Outcome::Win,
-        (a, b) if a == b => Outcome::Draw,
-        _ => Outcome::Loss,
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Encoding {
+    theirs: HashMap<char, Shape>,
+    yours: HashMap<char, Strategy>,
+}
+
+impl Encoding {
+    pub fn new() -> Self {
+        Encoding::default()
     }
+
+    pub fn theirs(mut self, symbol: char, shape: Shape) -> Self {
+        self.theirs.insert(symbol, shape);
+        self
+    }
+
+    pub fn yours(mut self, symbol: char, strategy: Strategy) -> Self {
+        self.yours.insert(symbol, strategy);
+        self
+    }
+
+    // their symbols then yours map onto the game's shapes in order
+    pub fn shapes(theirs: &str, yours: &str) -> Self {
+        let encoding = theirs
+            .chars()
+            .enumerate()
+            .fold(Encoding::new(), |e, (shape, c)| e.theirs(c, shape));
+
+        yours
+            .chars()
+            .enumerate()
+            .fold(encoding, |e, (shape, c)| e.yours(c, Strategy::Shape(shape)))
+    }
+}
+
+// one way of reading the second column of a strategy guide, and what it would score
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoding {
+    pub strategies: Vec<(char, Strategy)>, // ordered by symbol
+    pub score: usize,
 }
 
-fn round_score(moves: (Move, Move)) -> usize {
-    let (their_move, your_move) = moves;
-    let outcome = round_outcome(&your_move, &their_move);
-    let mut score = MOVE_SCORES[your_move as usize];
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    pub decodings: Vec<Decoding>, // every shape decoding, then every outcome decoding
+}
 
-    match outcome {
-        Outcome::Win => score += 6,
-        Outcome::Draw => score += 3,
-        _ => (),
+impl Analysis {
+    // the earliest decoding on ties
+    pub fn best(&self) -> Option<&Decoding> {
+        self.decodings
+            .iter()
+            .rev()
+            .max_by_key(|decoding| decoding.score)
     }
 
-    score
+    pub fn worst(&self) -> Option<&Decoding> {
+        self.decodings.iter().min_by_key(|decoding| decoding.score)
+    }
 }
 
-fn strategic_move(moves: (Move, Move)) -> Move {
-    let (their_move, encoded_outcome) = moves;
+/**
+ * Scores the guide under every way of assigning distinct shapes, or distinct
+ * outcomes, to the symbols in its second column, keeping `theirs` for the
+ * first one. Outcome decodings that ask for something the game can't deliver
+ * are left out.
+ */
+pub fn analyze(
+    game: &Game,
+    rounds: &[(char, char)],
+    theirs: &Encoding,
+) -> Result<Analysis, GameError> {
+    let symbols: Vec<char> = rounds.iter().map(|&(_, b)| b).sorted().dedup().collect();
+    let shapes = (0..game.shapes())
+        .permutations(symbols.len())
+        .map(|shapes| shapes.into_iter().map(Strategy::Shape).collect::<Vec<_>>());
+    let outcomes = OUTCOMES.iter().permutations(symbols.len()).map(|outcomes| {
+        outcomes
+            .into_iter()
+            .map(|&outcome| Strategy::Outcome(outcome))
+            .collect::<Vec<_>>()
+    });
+    let mut decodings = Vec::new();
+
+    for strategies in shapes.chain(outcomes) {
+        let strategies: Vec<(char, Strategy)> = symbols.iter().copied().zip(strategies).collect();
+        let encoding = strategies
+            .iter()
+            .fold(theirs.clone(), |e, &(c, strategy)| e.yours(c, strategy));
 
-    match encoded_outcome {
-        Move::Boulder => match their_move {
-            Move::Boulder => Move::Shears,
-            Move::Parchment => Move::Boulder,
-            Move::Shears => Move::Parchment,
-        },
-        Move::Shears => match their_move {
-            Move::Boulder => Move::Parchment,
-            Move::Parchment => Move::Shears,
-            Move::Shears => Move::Boulder,
-        },
-        Move::Parchment => their_move,
+        match game.play(rounds, &encoding) {
+            Ok(score) => decodings.push(Decoding { strategies, score }),
+            Err(GameError::NoResponse { .. }) => continue,
+            Err(e) => return Err(e),
+        }
     }
+
+    Ok(Analysis { decodings })
+}
+
+#[aoc_generator(day2)]
+pub fn input_generator(input: &str) -> Result<Vec<(char, char)>, GameError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let mut chars = line.chars();
+
+            match (chars.next(), chars.next(), chars.next(), chars.next()) {
+                (Some(a), Some(' '), Some(b), None) => Ok((a, b)),
+                _ => Err(GameError::InvalidRound(i + 1)),
+            }
+        })
+        .collect()
+}
+
+#[aoc(day2, part1)]
+pub fn part1(rounds: &[(char, char)]) -> Result<usize, GameError> {
+    Game::rock_paper_scissors().play(rounds, &Encoding::shapes("ABC", "XYZ"))
+}
+
+#[aoc(day2, part2)]
+pub fn part2(rounds: &[(char, char)]) -> Result<usize, GameError> {
+    let encoding = Encoding::shapes("ABC", "")
+        .yours('X', Strategy::Outcome(Outcome::Loss))
+        .yours('Y', Strategy::Outcome(Outcome::Draw))
+        .yours('Z', Strategy::Outcome(Outcome::Win));
+
+    Game::rock_paper_scissors().play(rounds, &encoding)
 }
 
 #[cfg(test)]
@@ -150,18 +394,164 @@ mod tests {
     #[test]
     fn input() {
         let input = "A B";
-        assert_eq!(input_generator(input), [(Move::Boulder, Move::Parchment)]);
+        assert_eq!(input_generator(input), Ok(vec![('A', 'B')]));
+        assert_eq!(input_generator("A B\nAB"), Err(GameError::InvalidRound(2)));
     }
 
     #[test]
     fn sample1() {
         let input = "A Y\nB X\nC Z";
-        assert_eq!(part1(&input_generator(input)), 15);
+        assert_eq!(part1(&input_generator(input).unwrap()), Ok(15));
     }
 
     #[test]
     fn sample2() {
         let input = "A Y\nB X\nC Z";
-        assert_eq!(part2(&input_generator(input)), 12);
+        assert_eq!(part2(&input_generator(input).unwrap()), Ok(12));
+    }
+
+    #[test]
+    fn part1_test() {
+        let input = input_generator(include_str!("../input/2022/day2.txt").trim()).unwrap();
+        assert_eq!(part1(&input), Ok(15337));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input = input_generator(include_str!("../input/2022/day2.txt").trim()).unwrap();
+        assert_eq!(part2(&input), Ok(11696));
+    }
+
+    #[test]
+    fn lizard_spock() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let shape = |name| game.shape(name).unwrap();
+        let wins = [
+            ("Scissors", "Paper"),
+            ("Paper", "Rock"),
+            ("Rock", "Lizard"),
+            ("Lizard", "Spock"),
+            ("Spock", "Scissors"),
+            ("Scissors", "Lizard"),
+            ("Lizard", "Paper"),
+            ("Paper", "Spock"),
+            ("Spock", "Rock"),
+            ("Rock", "Scissors"),
+        ];
+
+        for (a, b) in wins {
+            assert_eq!(game.outcome(shape(a), shape(b)), Outcome::Win);
+            assert_eq!(game.outcome(shape(b), shape(a)), Outcome::Loss);
+        }
+
+        // Paper and Lizard both beat Spock, Lizard scores more
+        assert_eq!(
+            game.response(shape("Spock"), Outcome::Win),
+            Some(shape("Lizard"))
+        );
+
+        let rounds = input_generator("S V\nS P\nL L").unwrap();
+        let encoding = Encoding::shapes("RPSVL", "RPSVL");
+
+        // 4 + 6, 2 + 0, 5 + 3
+        assert_eq!(game.play(&rounds, &encoding), Ok(20));
+    }
+
+    #[test]
+    fn rules_table() {
+        assert_eq!(
+            Game::new(&["a", "b"], &[("a", "c")]).unwrap_err(),
+            GameError::UnknownShape("c".to_string())
+        );
+        assert_eq!(
+            Game::new(&["a", "b"], &[("a", "b"), ("b", "a")]).unwrap_err(),
+            GameError::Contradiction("b".to_string(), "a".to_string())
+        );
+        assert_eq!(
+            Game::cyclic(&["a", "b"]).unwrap_err(),
+            GameError::EvenCycle(2)
+        );
+
+        // a lopsided game where the only win is impossible to get against `b`
+        let game = Game::new(&["a", "b"], &[("b", "a")])
+            .unwrap()
+            .with_shape_scores(&[10, 20])
+            .unwrap()
+            .with_outcome_scores([1, 0, 0]);
+        let encoding = Encoding::shapes("ab", "").yours('w', Strategy::Outcome(Outcome::Win));
+
+        assert_eq!(game.play(&[('a', 'w')], &encoding), Ok(21));
+        assert_eq!(
+            game.play(&[('a', 'w'), ('b', 'w')], &encoding),
+            Err(GameError::NoResponse {
+                round: 2,
+                outcome: Outcome::Win
+            })
+        );
+        assert_eq!(
+            game.play(&[('c', 'w')], &encoding),
+            Err(GameError::UnknownSymbol {
+                round: 1,
+                symbol: 'c'
+            })
+        );
+    }
+
+    #[test]
+    fn decodings() {
+        let rounds = input_generator("A Y\nB X\nC Z").unwrap();
+        let analysis = analyze(
+            &Game::rock_paper_scissors(),
+            &rounds,
+            &Encoding::shapes("ABC", ""),
+        )
+        .unwrap();
+
+        // 3! shape decodings and 3! outcome decodings
+        assert_eq!(analysis.decodings.len(), 12);
+        assert_eq!(analysis.decodings[0].score, 15);
+        assert_eq!(
+            analysis.decodings[0].strategies,
+            vec![
+                ('X', Strategy::Shape(0)),
+                ('Y', Strategy::Shape(1)),
+                ('Z', Strategy::Shape(2))
+            ]
+        );
+
+        // winning every round, which only a shape decoding can do here
+        let best = analysis.best().unwrap();
+
+        assert_eq!(best.score, 6 * 3 + 2 + 3 + 1);
+        assert_eq!(
+            best.strategies,
+            vec![
+                ('X', Strategy::Shape(2)),
+                ('Y', Strategy::Shape(1)),
+                ('Z', Strategy::Shape(0))
+            ]
+        );
+        assert_eq!(analysis.worst().unwrap().score, 3 + 1 + 2);
+    }
+
+    #[test]
+    fn decodings_test() {
+        let input = input_generator(include_str!("../input/2022/day2.txt").trim()).unwrap();
+        let analysis = analyze(
+            &Game::rock_paper_scissors(),
+            &input,
+            &Encoding::shapes("ABC", ""),
+        )
+        .unwrap();
+        let scores: Vec<usize> = analysis.decodings.iter().map(|d| d.score).collect();
+
+        assert!(scores.contains(&15337));
+        assert!(scores.contains(&11696));
+        assert!(scores
+            .iter()
+            .all(|&score| score <= analysis.best().unwrap().score));
+        assert!(scores
+            .iter()
+            .all(|&score| score >= analysis.worst().unwrap().score));
     }
 }
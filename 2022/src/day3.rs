@@ -56,116 +56,295 @@ Priorities for these items must still be found to organize the sticker attachmen
 Find the item type that corresponds to the badges of each three-Elf group. What is the sum of the priorities of those item types?
 */
 
-type Inventory = [u8; 52];
+use std::error::Error;
+use std::fmt;
+use std::ops::BitAnd;
+
+#[derive(Debug, PartialEq)]
+pub enum RucksackError {
+    InvalidItem { line: usize, item: char },
+    EmptyRucksack { line: usize },
+    UnevenCompartments { line: usize, compartments: usize },
+    InvalidGroupSize { size: usize },
+    IncompleteGroup { line: usize }, // the last group has fewer rucksacks than the rest
+    MissingBadge { line: usize },    // first line of the group with no item in common
+    AmbiguousBadge { line: usize, priorities: Vec<u8> },
+}
 
-fn parse_inventories(encoded: &str, split: bool) -> Vec<Inventory> {
-    let inventories: Vec<Inventory> = if split {
-        let (a, b) = encoded.split_at(encoded.len() / 2); // 💀
-        vec![parse_items(a), parse_items(b)]
-    } else {
-        vec![parse_items(encoded)]
-    };
+impl Error for RucksackError {}
 
-    inventories
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RucksackError::InvalidItem { line, item } => {
+                write!(f, "Invalid item {:?} on line {}", item, line)
+            }
+            RucksackError::EmptyRucksack { line } => {
+                write!(f, "Rucksack on line {} has no items", line)
+            }
+            RucksackError::InvalidGroupSize { size } => {
+                write!(f, "Groups of {} rucksacks can't have a badge", size)
+            }
+            RucksackError::UnevenCompartments { line, compartments } => write!(
+                f,
+                "Rucksack on line {} can't be split into {} equal compartments",
+                line, compartments
+            ),
+            RucksackError::IncompleteGroup { line } => {
+                write!(f, "Group starting on line {} is missing rucksacks", line)
+            }
+            RucksackError::MissingBadge { line } => {
+                write!(f, "Group starting on line {} has no badge", line)
+            }
+            RucksackError::AmbiguousBadge { line, priorities } => write!(
+                f,
+                "Group starting on line {} has several possible badges, priorities {:?}",
+                line, priorities
+            ),
+        }
+    }
 }
 
-#[aoc_generator(day3)]
-pub fn input_generator(input: &str) -> String {
-    input.to_string() // 🤷
+// a set of item types, bit `p` is set when there's an item with priority `p`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Items(u64);
+
+impl Items {
+    pub fn all() -> Self {
+        Items(!0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn priorities(&self) -> Vec<u8> {
+        (1..=52).filter(|p| self.0 >> p & 1 == 1).collect()
+    }
 }
 
-#[aoc(day3, part1)]
-pub fn part1(input: &str) -> usize {
-    input
-        .lines()
-        .map(|line| parse_inventories(line, true))
-        .filter_map(|invs| find_badge(&invs))
-        .map(|idx| idx + 1)
-        .sum()
+impl BitAnd for Items {
+    type Output = Items;
+
+    fn bitand(self, other: Items) -> Items {
+        Items(self.0 & other.0)
+    }
 }
 
-#[aoc(day3, part2)]
-pub fn part2(input: &str) -> usize {
+impl FromIterator<u8> for Items {
+    fn from_iter<I: IntoIterator<Item = u8>>(priorities: I) -> Self {
+        Items(priorities.into_iter().fold(0, |set, p| set | 1 << p))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Rucksack {
+    line: usize,
+    priorities: Vec<u8>, // in the order the items were packed
+}
+
+impl Rucksack {
+    pub fn items(&self) -> Items {
+        self.priorities.iter().copied().collect()
+    }
+
+    #[allow(clippy::manual_is_multiple_of)] // `is_multiple_of` needs Rust 1.87
+    pub fn compartments(&self, n: usize) -> Result<Vec<Items>, RucksackError> {
+        if self.priorities.is_empty() {
+            return Err(RucksackError::EmptyRucksack { line: self.line });
+        }
+
+        if n == 0 || self.priorities.len() % n != 0 {
+            return Err(RucksackError::UnevenCompartments {
+                line: self.line,
+                compartments: n,
+            });
+        }
+
+        Ok(self
+            .priorities
+            .chunks(self.priorities.len() / n)
+            .map(|compartment| compartment.iter().copied().collect())
+            .collect())
+    }
+}
+
+#[aoc_generator(day3)]
+pub fn input_generator(input: &str) -> Result<Vec<Rucksack>, RucksackError> {
     input
         .lines()
-        .flat_map(|line| parse_inventories(line, false))
-        .collect::<Vec<Inventory>>()
-        .chunks(3)
-        .filter_map(|sacks| find_badge(sacks))
-        .map(|idx| idx + 1)
-        .sum()
+        .enumerate()
+        .map(|(i, line)| {
+            let priorities = line
+                .trim()
+                .chars()
+                .map(|item| {
+                    item_to_priority(item).ok_or(RucksackError::InvalidItem { line: i + 1, item })
+                })
+                .collect::<Result<_, _>>()?;
+
+            Ok(Rucksack {
+                line: i + 1,
+                priorities,
+            })
+        })
+        .collect()
 }
 
-fn parse_items(input: &str) -> Inventory {
-    let mut items = [0u8; 52];
+#[aoc(day3, part1)]
+pub fn part1(rucksacks: &[Rucksack]) -> Result<usize, RucksackError> {
+    compartment_priorities(rucksacks, 2)
+}
 
-    for c in input.trim().chars() {
-        let priority = item_to_priority(c);
-        items[priority - 1] += 1;
-    }
+#[aoc(day3, part2)]
+pub fn part2(rucksacks: &[Rucksack]) -> Result<usize, RucksackError> {
+    group_priorities(rucksacks, 3)
+}
 
-    items
+// sum of the one item type found in every compartment of each rucksack
+pub fn compartment_priorities(
+    rucksacks: &[Rucksack],
+    compartments: usize,
+) -> Result<usize, RucksackError> {
+    rucksacks
+        .iter()
+        .map(|rucksack| find_badge(&rucksack.compartments(compartments)?, rucksack.line))
+        .map(|badge| badge.map(|p| p as usize))
+        .sum()
 }
 
-fn item_to_priority(item: char) -> usize {
-    match item {
-        item if item >= 'a' && item <= 'z' => (item as u8 - 96) as usize,
-        item if item >= 'A' && item <= 'Z' => (item as u8 - 38) as usize,
-        _ => panic!("Invalid item!"),
+// sum of the one item type carried by every rucksack in each group of `size`
+pub fn group_priorities(rucksacks: &[Rucksack], size: usize) -> Result<usize, RucksackError> {
+    if size == 0 {
+        return Err(RucksackError::InvalidGroupSize { size });
     }
-}
 
-fn find_badge(invs: &[Inventory]) -> Option<usize> {
-    assert!(
-        invs.len() > 1,
-        "Must have more than 1 inventory to find badge"
-    );
-    let first = invs[0];
-
-    // for each priority
-    for i in 0..first.len() {
-        // if the first inventory has no items at that priority, move on to the next priority
-        if first[i] == 0 {
-            continue;
-        }
+    rucksacks
+        .chunks(size)
+        .map(|group| {
+            let line = group[0].line;
 
-        // for each of the rest of the inventories
-        for j in 1..invs.len() {
-            // if this inventory has no items with priority `i`, it can't be the badge
-            if invs[j][i] == 0 {
-                break;
+            if group.len() < size {
+                return Err(RucksackError::IncompleteGroup { line });
             }
 
-            // if we've made it here, then we've checked all the `invs` and they all
-            // have an item with priority `i`, so it must be the badge
-            if j == invs.len() - 1 {
-                return Some(i);
-            }
-        }
+            let items: Vec<Items> = group.iter().map(Rucksack::items).collect();
+
+            find_badge(&items, line).map(|p| p as usize)
+        })
+        .sum()
+}
+
+pub fn item_to_priority(item: char) -> Option<u8> {
+    match item {
+        'a'..='z' => Some(item as u8 - b'a' + 1),
+        'A'..='Z' => Some(item as u8 - b'A' + 27),
+        _ => None,
     }
+}
 
-    None
+fn find_badge(sets: &[Items], line: usize) -> Result<u8, RucksackError> {
+    let common = sets
+        .iter()
+        .fold(Items::all(), |common, &items| common & items);
+
+    match common.priorities().as_slice() {
+        [badge] => Ok(*badge),
+        [] => Err(RucksackError::MissingBadge { line }),
+        priorities => Err(RucksackError::AmbiguousBadge {
+            line,
+            priorities: priorities.to_vec(),
+        }),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp\njqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL\nPmmdzqPrVvPwwTWBwg\nwMqvLMZHhHMvwLHjbvcjnnSBnvTQFn\nttgJtRGJQctTZtZT\nCrZsJsPPZsGzwwsLwLmpwMDw";
+
     #[test]
     fn input() {
-        let input = "vJrwpWtwJgWrhcsFMMfFFhFp\njqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL\nPmmdzqPrVvPwwTWBwg\nwMqvLMZHhHMvwLHjbvcjnnSBnvTQFn\nttgJtRGJQctTZtZT\nCrZsJsPPZsGzwwsLwLmpwMDw";
-        assert_eq!(input_generator(input), input);
+        let rucksacks = input_generator(SAMPLE).unwrap();
+        let compartments = rucksacks[0].compartments(2).unwrap();
+
+        assert_eq!(rucksacks.len(), 6);
+        assert_eq!((compartments[0] & compartments[1]).priorities(), vec![16]);
+        assert_eq!(rucksacks[0].items().len(), 14);
+        assert_eq!(
+            input_generator("ab\nc-d"),
+            Err(RucksackError::InvalidItem { line: 2, item: '-' })
+        );
     }
 
     #[test]
     fn sample1() {
-        let input = "vJrwpWtwJgWrhcsFMMfFFhFp\njqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL\nPmmdzqPrVvPwwTWBwg\nwMqvLMZHhHMvwLHjbvcjnnSBnvTQFn\nttgJtRGJQctTZtZT\nCrZsJsPPZsGzwwsLwLmpwMDw";
-        assert_eq!(part1(&input_generator(input)), 157);
+        assert_eq!(part1(&input_generator(SAMPLE).unwrap()), Ok(157));
     }
 
     #[test]
     fn sample2() {
-        let input = "vJrwpWtwJgWrhcsFMMfFFhFp\njqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL\nPmmdzqPrVvPwwTWBwg\nwMqvLMZHhHMvwLHjbvcjnnSBnvTQFn\nttgJtRGJQctTZtZT\nCrZsJsPPZsGzwwsLwLmpwMDw";
-        assert_eq!(part2(&input_generator(input)), 70);
+        assert_eq!(part2(&input_generator(SAMPLE).unwrap()), Ok(70));
+    }
+
+    #[test]
+    fn part1_test() {
+        let input = input_generator(include_str!("../input/2022/day3.txt").trim()).unwrap();
+        assert_eq!(part1(&input), Ok(8349));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input = input_generator(include_str!("../input/2022/day3.txt").trim()).unwrap();
+        assert_eq!(part2(&input), Ok(2681));
+    }
+
+    #[test]
+    fn configurable_sizes() {
+        let rucksacks = input_generator("abcXaY\nXdeZff").unwrap();
+
+        // split in three, `ab`, `cX` and `aY` have nothing in common
+        assert_eq!(
+            compartment_priorities(&rucksacks[..1], 3),
+            Err(RucksackError::MissingBadge { line: 1 })
+        );
+        assert_eq!(
+            compartment_priorities(&rucksacks, 4),
+            Err(RucksackError::UnevenCompartments {
+                line: 1,
+                compartments: 4
+            })
+        );
+        assert_eq!(group_priorities(&rucksacks, 2), Ok(50));
+        assert_eq!(
+            group_priorities(&input_generator("ab\nab\nab\nab").unwrap(), 3),
+            Err(RucksackError::AmbiguousBadge {
+                line: 1,
+                priorities: vec![1, 2]
+            })
+        );
+        assert_eq!(
+            group_priorities(&input_generator("ab\nbc\nbd\nbe").unwrap(), 3),
+            Err(RucksackError::IncompleteGroup { line: 4 })
+        );
+        assert_eq!(
+            group_priorities(&rucksacks, 0),
+            Err(RucksackError::InvalidGroupSize { size: 0 })
+        );
+    }
+
+    #[test]
+    fn blank_line() {
+        let rucksacks = input_generator("vJrwpWtwJgWrhcsFMMfFFhFp\n\nPmmdzqPrVvPwwTWBwg").unwrap();
+
+        assert_eq!(
+            compartment_priorities(&rucksacks, 2),
+            Err(RucksackError::EmptyRucksack { line: 2 })
+        );
     }
 }
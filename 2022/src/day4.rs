@@ -56,76 +56,132 @@ In how many assignment pairs do the ranges overlap?
 
 */
 
+use crate::interval::{overlapping_pairs, Interval, IntervalSet};
+use std::error::Error;
+use std::fmt;
+
+type Section = u32;
+type Assignment = Interval<Section>;
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+}
+
+impl Error for ParseError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error parsing section assignments on line {}", self.line)
+    }
+}
+
 #[aoc_generator(day4)]
-pub fn input_generator(input: &str) -> Vec<((u8, u8), (u8, u8))> {
+pub fn input_generator(input: &str) -> Result<Vec<(Assignment, Assignment)>, ParseError> {
     input
         .lines()
-        .map(|line| {
-            let (a, b) = line.split_once(',').expect("Invalid input");
-            let (a1, a2) = a.split_once('-').expect("Invalid input");
-            let (b1, b2) = b.split_once('-').expect("Invalid input");
-
-            (
-                (
-                    a1.parse().expect("Invalid input"),
-                    a2.parse().expect("Invalid input"),
-                ),
-                (
-                    b1.parse().expect("Invalid input"),
-                    b2.parse().expect("Invalid input"),
-                ),
-            )
+        .enumerate()
+        .map(|(i, line)| {
+            let error = || ParseError { line: i + 1 };
+            let (a, b) = line.split_once(',').ok_or_else(error)?;
+
+            Ok((
+                parse_assignment(a).ok_or_else(error)?,
+                parse_assignment(b).ok_or_else(error)?,
+            ))
         })
         .collect()
 }
 
+fn parse_assignment(s: &str) -> Option<Assignment> {
+    let (start, end) = s.split_once('-')?;
+
+    Interval::new(start.trim().parse().ok()?, end.trim().parse().ok()?)
+}
+
 #[aoc(day4, part1)]
-pub fn part1(pairs: &[((u8, u8), (u8, u8))]) -> usize {
-    pairs.iter().filter(|(a, b)| ranges_nested(a, b)).count()
+pub fn part1(pairs: &[(Assignment, Assignment)]) -> usize {
+    pairs
+        .iter()
+        .filter(|(a, b)| a.covers(b) || b.covers(a))
+        .count()
 }
 
 #[aoc(day4, part2)]
-pub fn part2(pairs: &[((u8, u8), (u8, u8))]) -> usize {
-    pairs.iter().filter(|(a, b)| ranges_overlap(a, b)).count()
+pub fn part2(pairs: &[(Assignment, Assignment)]) -> usize {
+    pairs.iter().filter(|(a, b)| a.overlaps(b)).count()
 }
 
-fn ranges_nested(a: &(u8, u8), b: &(u8, u8)) -> bool {
-    (a.0 <= b.0 && a.1 >= b.1) || (b.0 <= a.0 && b.1 >= a.1)
+// every elf's assignment merged together
+pub fn assigned(pairs: &[(Assignment, Assignment)]) -> IntervalSet<Section> {
+    pairs.iter().flat_map(|&(a, b)| [a, b]).collect()
 }
 
-fn ranges_overlap(a: &(u8, u8), b: &(u8, u8)) -> bool {
-    (a.0 <= b.0 && b.0 <= a.1) || (b.0 <= a.0 && a.0 <= b.1)
+// sections nobody has been assigned, between the lowest and highest assigned ones
+pub fn unassigned(pairs: &[(Assignment, Assignment)]) -> IntervalSet<Section> {
+    assigned(pairs).gaps()
+}
+
+// every pair of elves, from any two pairs, with overlapping assignments
+pub fn overlapping_elves(pairs: &[(Assignment, Assignment)]) -> Vec<(usize, usize)> {
+    let elves: Vec<Assignment> = pairs.iter().flat_map(|&(a, b)| [a, b]).collect();
+
+    overlapping_pairs(&elves)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SAMPLE: &str = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8";
+
+    fn assignment(start: Section, end: Section) -> Assignment {
+        Interval::new(start, end).unwrap()
+    }
+
     #[test]
     fn input() {
-        let input = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8";
         assert_eq!(
-            input_generator(input),
+            input_generator(SAMPLE).unwrap()[..2],
             [
-                ((2, 4), (6, 8)),
-                ((2, 3), (4, 5)),
-                ((5, 7), (7, 9)),
-                ((2, 8), (3, 7)),
-                ((6, 6), (4, 6)),
-                ((2, 6), (4, 8))
+                (assignment(2, 4), assignment(6, 8)),
+                (assignment(2, 3), assignment(4, 5)),
             ]
         );
+        assert_eq!(
+            input_generator("1-2,3-4\n5-4,1-1"),
+            Err(ParseError { line: 2 })
+        );
     }
 
     #[test]
     fn sample1() {
-        let input = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8";
-        assert_eq!(part1(&input_generator(input)), 2);
+        assert_eq!(part1(&input_generator(SAMPLE).unwrap()), 2);
     }
 
     #[test]
     fn sample2() {
-        let input = "2-4,6-8\n2-3,4-5\n5-7,7-9\n2-8,3-7\n6-6,4-6\n2-6,4-8";
-        assert_eq!(part2(&input_generator(input)), 4);
+        assert_eq!(part2(&input_generator(SAMPLE).unwrap()), 4);
+    }
+
+    #[test]
+    fn part1_test() {
+        let input = input_generator(include_str!("../input/2022/day4.txt").trim()).unwrap();
+        assert_eq!(part1(&input), 536);
+    }
+
+    #[test]
+    fn part2_test() {
+        let input = input_generator(include_str!("../input/2022/day4.txt").trim()).unwrap();
+        assert_eq!(part2(&input), 845);
+    }
+
+    #[test]
+    fn fleet() {
+        let pairs = input_generator("1-3,300-400\n10-20,15-299").unwrap();
+
+        assert_eq!(assigned(&pairs).coverage(), 3 + 290 + 101);
+        assert_eq!(unassigned(&pairs).intervals(), &[assignment(4, 9)]);
+        assert_eq!(overlapping_elves(&pairs), vec![(2, 3)]);
     }
 }
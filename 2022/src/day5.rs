@@ -95,111 +95,388 @@ In this example, the CrateMover 9001 has put the crates in a totally different o
 
 Before the rearrangement process finishes, update your simulation so that the Elves know where they should stand to be ready to unload the final supplies. After the rearrangement procedure completes, what crate ends up on top of each stack?
 */
-type Supplies = Vec<Stack>;
-type Stack = Vec<char>;
 
-type Op = (
-    usize, /* amount */
-    usize, /* source */
-    usize, /* dest */
-);
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
-#[aoc_generator(day5)]
-pub fn input_generator(input: &str) -> (Supplies, Vec<Op>) {
-    let (sup, ops) = input.split_once("\n\n").expect("Invalid input");
-    (parse_supplies(sup), parse_operations(ops))
+type Stack = Vec<String>; // crate labels, bottom to top
+
+#[derive(Debug, PartialEq)]
+pub enum CraneError {
+    InvalidInput,
+    InvalidDrawing { line: usize },
+    InvalidMove { text: String }, // the move as written in the procedure
+    NoSuchStack { step: usize, stack: String },
+    NotEnoughCrates { step: usize, stack: String },
 }
 
-fn parse_supplies(sup: &str) -> Supplies {
-    let rows: Vec<Vec<char>> = sup.lines().rev().map(|l| l.chars().collect()).collect();
+impl Error for CraneError {}
 
-    transpose(&rows)
-        .into_iter()
-        .filter(|l| l[0] != ' ')
-        .map(|l| l.into_iter().filter(|c| c.is_alphabetic()).collect())
-        .collect()
+impl fmt::Display for CraneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CraneError::InvalidInput => write!(f, "Error parsing drawing and procedure"),
+            CraneError::InvalidDrawing { line } => {
+                write!(f, "Error parsing line {} of the drawing", line)
+            }
+            CraneError::InvalidMove { text } => write!(f, "Error parsing move {:?}", text),
+            CraneError::NoSuchStack { step, stack } => {
+                write!(f, "Move {} uses stack {}, which doesn't exist", step, stack)
+            }
+            CraneError::NotEnoughCrates { step, stack } => {
+                write!(
+                    f,
+                    "Move {} takes more crates than stack {} has",
+                    step, stack
+                )
+            }
+        }
+    }
 }
 
-fn parse_operations(ops: &str) -> Vec<Op> {
-    ops.lines()
-        .map(|line| {
-            let words: Vec<&str> = line.split_ascii_whitespace().collect();
-            (
-                words[1].parse().unwrap(),
-                words[3].parse().unwrap(),
-                words[5].parse().unwrap(),
-            )
-        })
-        .collect()
+// where things go when the stacks are drawn, all in chars from the start of the line
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    crate_starts: Vec<usize>, // column of each stack's opening bracket
+    label_starts: Vec<usize>,
+    width: usize, // longest line in the drawing
+    padded: bool, // every line is padded with spaces to `width`
 }
 
-#[aoc(day5, part1)]
-pub fn part1(input: &(Supplies, Vec<Op>)) -> String {
-    let (mut supplies, operations) = input.clone();
+/**
+ * The stacks of crates along with how they were drawn, so a parsed drawing
+ * renders back exactly as it was, and a rearranged one renders in the same
+ * style. Stacks are found from the label row, any crate that overlaps a label
+ * (or failing that, is closest to one) belongs to that stack.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Supplies {
+    stacks: Vec<Stack>,
+    labels: Vec<String>,
+    layout: Layout,
+}
 
-    for (count, source, dest) in operations {
-        for _ in 0..count {
-            if let Some(item) = supplies[source - 1].pop() {
-                supplies[dest - 1].push(item);
-            }
+impl Supplies {
+    pub fn stacks(&self) -> &[Stack] {
+        &self.stacks
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn stack(&self, label: &str) -> Option<&Stack> {
+        self.position(label).map(|i| &self.stacks[i])
+    }
+
+    pub fn top_crates(&self) -> String {
+        self.stacks
+            .iter()
+            .filter_map(|stack| stack.last().map(String::as_str))
+            .collect()
+    }
+
+    pub fn render(&self) -> String {
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or(0);
+        let mut lines: Vec<String> = (0..height)
+            .rev()
+            .map(|level| {
+                let cells = self.stacks.iter().enumerate().filter_map(|(i, stack)| {
+                    let item = stack.get(level)?;
+                    Some((self.layout.crate_starts[i], format!("[{}]", item)))
+                });
+
+                self.line(cells)
+            })
+            .collect();
+
+        lines.push(
+            self.line(
+                self.labels
+                    .iter()
+                    .enumerate()
+                    .map(|(i, label)| (self.layout.label_starts[i], label.clone())),
+            ),
+        );
+
+        lines.join("\n")
+    }
+
+    fn position(&self, label: &str) -> Option<usize> {
+        self.labels.iter().position(|l| l == label)
+    }
+
+    // lays out cells at their columns, nudging any that would run into the one before
+    fn line(&self, cells: impl Iterator<Item = (usize, String)>) -> String {
+        let mut line = String::new();
+        let mut len = 0;
+
+        for (start, cell) in cells {
+            let gap = if len == 0 { 0 } else { 1 };
+            let column = start.max(len + gap);
+
+            line.push_str(&" ".repeat(column - len));
+            line.push_str(&cell);
+            len = column + cell.chars().count();
+        }
+
+        if self.layout.padded && len < self.layout.width {
+            line.push_str(&" ".repeat(self.layout.width - len));
         }
+
+        line
     }
+}
 
-    supplies
-        .iter()
-        .filter_map(|stack| stack.last().cloned())
-        .map(|c| c.to_ascii_uppercase())
-        .collect()
+// tokens on a line with the column they start at
+fn tokens(line: &[char], number: usize) -> Result<Vec<(usize, String)>, CraneError> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < line.len() {
+        if line[i] == ' ' {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+
+        while i < line.len() && line[i] != ' ' {
+            i += 1;
+        }
+
+        // crate labels can't contain spaces, but they can contain brackets
+        if line[start] == '[' && line[i - 1] != ']' {
+            return Err(CraneError::InvalidDrawing { line: number });
+        }
+
+        tokens.push((start, line[start..i].iter().collect()));
+    }
+
+    Ok(tokens)
 }
 
-#[aoc(day5, part2)]
-pub fn part2(input: &(Supplies, Vec<Op>)) -> String {
-    let (mut supplies, operations) = input.clone();
+impl FromStr for Supplies {
+    type Err = CraneError;
 
-    for (count, source, dest) in operations {
-        let mut op_items = Vec::with_capacity(count);
+    fn from_str(drawing: &str) -> Result<Self, CraneError> {
+        let lines: Vec<Vec<char>> = drawing.lines().map(|l| l.chars().collect()).collect();
+        let (label_row, crate_rows) = lines
+            .split_last()
+            .ok_or(CraneError::InvalidDrawing { line: 1 })?;
+        let labels = tokens(label_row, lines.len())?;
 
-        for _ in 0..count {
-            if let Some(item) = supplies[source - 1].pop() {
-                op_items.push(item);
-            }
+        if labels.is_empty() {
+            return Err(CraneError::InvalidDrawing { line: lines.len() });
         }
 
-        loop {
-            if op_items.is_empty() {
-                break;
+        let spans: Vec<(usize, usize)> = labels
+            .iter()
+            .map(|(start, label)| (*start, start + label.chars().count()))
+            .collect();
+        let mut stacks: Vec<Stack> = vec![Vec::new(); labels.len()];
+        let mut crate_starts: Vec<Option<usize>> = vec![None; labels.len()];
+
+        // bottom row first, so stacks build up from the floor
+        for (i, row) in crate_rows.iter().enumerate().rev() {
+            for (start, token) in tokens(row, i + 1)? {
+                let len = token.chars().count();
+
+                if !token.starts_with('[') || len < 3 {
+                    return Err(CraneError::InvalidDrawing { line: i + 1 });
+                }
+
+                let end = start + len;
+                let stack = spans
+                    .iter()
+                    .position(|&(s, e)| s < end && start < e)
+                    .unwrap_or_else(|| {
+                        let middle = (start + end) as isize;
+                        (0..spans.len())
+                            .min_by_key(|&j| (middle - (spans[j].0 + spans[j].1) as isize).abs())
+                            .unwrap_or(0)
+                    });
+
+                stacks[stack].push(token[1..token.len() - 1].to_string());
+                crate_starts[stack] = Some(crate_starts[stack].map_or(start, |s| s.min(start)));
             }
+        }
 
-            supplies[dest - 1].push(op_items.pop().unwrap());
+        let width = lines.iter().map(Vec::len).max().unwrap_or(0);
+
+        Ok(Supplies {
+            stacks,
+            labels: labels.iter().map(|(_, label)| label.clone()).collect(),
+            layout: Layout {
+                crate_starts: (0..spans.len())
+                    .map(|i| {
+                        crate_starts[i].unwrap_or_else(|| {
+                            // an empty stack lines its crates up with its label the same way
+                            // the closest stack with crates does
+                            let offset = (0..spans.len())
+                                .filter_map(|j| Some((i.abs_diff(j), crate_starts[j]?, spans[j].0)))
+                                .min()
+                                .map_or(1, |(_, crate_start, label_start)| {
+                                    label_start as isize - crate_start as isize
+                                });
+
+                            (spans[i].0 as isize - offset).max(0) as usize
+                        })
+                    })
+                    .collect(),
+                label_starts: spans.iter().map(|&(s, _)| s).collect(),
+                padded: lines.iter().all(|line| line.len() == width),
+                width,
+            },
+        })
+    }
+}
+
+impl fmt::Display for Supplies {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+// stacks are referred to by their label in the drawing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Move {
+    pub count: usize,
+    pub from: String,
+    pub to: String,
+}
+
+impl FromStr for Move {
+    type Err = CraneError;
+
+    fn from_str(s: &str) -> Result<Self, CraneError> {
+        let invalid = || CraneError::InvalidMove {
+            text: s.to_string(),
+        };
+
+        match s.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            ["move", count, "from", from, "to", to] => Ok(Move {
+                count: count.parse().map_err(|_| invalid())?,
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+            _ => Err(invalid()),
         }
     }
+}
 
-    supplies
-        .iter()
-        .filter_map(|stack| stack.last().cloned())
-        .collect()
+pub trait Crane {
+    // moves `count` crates off the top of `from` onto `to`, which are known to be valid
+    fn transfer(&self, stacks: &mut [Stack], count: usize, from: usize, to: usize);
+
+    fn execute(&self, supplies: &mut Supplies, m: &Move, step: usize) -> Result<(), CraneError> {
+        let find = |label: &String| {
+            supplies
+                .position(label)
+                .ok_or_else(|| CraneError::NoSuchStack {
+                    step,
+                    stack: label.clone(),
+                })
+        };
+        let (from, to) = (find(&m.from)?, find(&m.to)?);
+
+        if supplies.stacks[from].len() < m.count {
+            return Err(CraneError::NotEnoughCrates {
+                step,
+                stack: m.from.clone(),
+            });
+        }
+
+        self.transfer(&mut supplies.stacks, m.count, from, to);
+        Ok(())
+    }
 }
 
-fn transpose<T: Clone>(records: &Vec<Vec<T>>) -> Vec<Vec<T>> {
-    let mut transposed: Vec<Vec<T>> = vec![Vec::new(); records[0].len()];
+// moves crates one at a time, so a batch lands upside down
+pub struct CrateMover9000;
 
-    for record in records {
-        for (index, element) in record.iter().enumerate() {
-            transposed[index].push(element.clone());
+impl Crane for CrateMover9000 {
+    fn transfer(&self, stacks: &mut [Stack], count: usize, from: usize, to: usize) {
+        for _ in 0..count {
+            if let Some(item) = stacks[from].pop() {
+                stacks[to].push(item);
+            }
         }
     }
+}
+
+// moves a batch of crates all at once, keeping their order
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn transfer(&self, stacks: &mut [Stack], count: usize, from: usize, to: usize) {
+        let at = stacks[from].len() - count;
+        let items = stacks[from].split_off(at);
+
+        stacks[to].extend(items);
+    }
+}
+
+#[aoc_generator(day5)]
+pub fn input_generator(input: &str) -> Result<(Supplies, Vec<Move>), CraneError> {
+    let (sup, ops) = input.split_once("\n\n").ok_or(CraneError::InvalidInput)?;
+
+    Ok((sup.parse()?, parse_operations(ops)?))
+}
+
+fn parse_operations(ops: &str) -> Result<Vec<Move>, CraneError> {
+    ops.lines().map(str::parse).collect()
+}
+
+#[aoc(day5, part1)]
+pub fn part1(input: &(Supplies, Vec<Move>)) -> Result<String, CraneError> {
+    Ok(simulate(&CrateMover9000, &input.0, &input.1)?.top_crates())
+}
+
+#[aoc(day5, part2)]
+pub fn part2(input: &(Supplies, Vec<Move>)) -> Result<String, CraneError> {
+    Ok(simulate(&CrateMover9001, &input.0, &input.1)?.top_crates())
+}
+
+pub fn simulate(
+    crane: &impl Crane,
+    supplies: &Supplies,
+    moves: &[Move],
+) -> Result<Supplies, CraneError> {
+    let mut supplies = supplies.clone();
+
+    for (i, m) in moves.iter().enumerate() {
+        crane.execute(&mut supplies, m, i + 1)?;
+    }
 
-    transposed
+    Ok(supplies)
+}
+
+// the supplies after each move
+pub fn trace(
+    crane: &impl Crane,
+    supplies: &Supplies,
+    moves: &[Move],
+) -> Result<Vec<Supplies>, CraneError> {
+    let mut supplies = supplies.clone();
+
+    moves
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            crane.execute(&mut supplies, m, i + 1)?;
+            Ok(supplies.clone())
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn input() {
-        let input = "    [D]
-[N] [C]
+    const SAMPLE: &str = "    [D]    
+[N] [C]    
 [Z] [M] [P]
  1   2   3 
 
@@ -208,42 +485,181 @@ move 3 from 1 to 3
 move 2 from 2 to 1
 move 1 from 1 to 2";
 
+    fn mv(count: usize, from: &str, to: &str) -> Move {
+        Move {
+            count,
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    fn stacks(stacks: &[&[&str]]) -> Vec<Stack> {
+        stacks
+            .iter()
+            .map(|stack| stack.iter().map(|item| item.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn input() {
+        let (supplies, moves) = input_generator(SAMPLE).unwrap();
+
         assert_eq!(
-            input_generator(input),
-            (
-                vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P'],],
-                vec![(1, 2, 1), (3, 1, 3), (2, 2, 1), (1, 1, 2)]
-            )
+            supplies.stacks(),
+            stacks(&[&["Z", "N"], &["M", "C", "D"], &["P"]])
+        );
+        assert_eq!(supplies.labels(), ["1", "2", "3"]);
+        assert_eq!(
+            moves,
+            vec![
+                mv(1, "2", "1"),
+                mv(3, "1", "3"),
+                mv(2, "2", "1"),
+                mv(1, "1", "2")
+            ]
+        );
+        assert_eq!(
+            input_generator("[A]\n 1 \n\nmove 1 from 1 to 1\nmove one from 1 to 1"),
+            Err(CraneError::InvalidMove {
+                text: "move one from 1 to 1".to_string()
+            })
         );
     }
 
     #[test]
     fn sample1() {
-        let input = "    [D]
-[N] [C]
-[Z] [M] [P]
- 1   2   3 
+        assert_eq!(part1(&input_generator(SAMPLE).unwrap()).unwrap(), "CMZ");
+    }
 
-move 1 from 2 to 1
-move 3 from 1 to 3
-move 2 from 2 to 1
-move 1 from 1 to 2";
+    #[test]
+    fn sample2() {
+        assert_eq!(part2(&input_generator(SAMPLE).unwrap()).unwrap(), "MCD");
+    }
+
+    #[test]
+    fn part1_test() {
+        let input_text = include_str!("../input/2022/day5.txt").trim_end();
+        let input = input_generator(input_text).unwrap();
 
-        assert_eq!(part1(&input_generator(input)), "CMZ");
+        assert_eq!(part1(&input).unwrap(), "SVFDLGLWV");
     }
 
     #[test]
-    fn sample2() {
-        let input = "    [D]
-[N] [C]
-[Z] [M] [P]
- 1   2   3 
+    fn part2_test() {
+        let input_text = include_str!("../input/2022/day5.txt").trim_end();
+        let input = input_generator(input_text).unwrap();
 
-move 1 from 2 to 1
-move 3 from 1 to 3
-move 2 from 2 to 1
-move 1 from 1 to 2";
+        assert_eq!(part2(&input).unwrap(), "DCVTCVPCL");
+    }
+
+    #[test]
+    fn validation() {
+        let (supplies, _) = input_generator(SAMPLE).unwrap();
+
+        assert_eq!(
+            simulate(
+                &CrateMover9000,
+                &supplies,
+                &[mv(1, "1", "2"), mv(1, "4", "1")]
+            ),
+            Err(CraneError::NoSuchStack {
+                step: 2,
+                stack: "4".to_string()
+            })
+        );
+        assert_eq!(
+            simulate(&CrateMover9001, &supplies, &[mv(1, "3", "0")]),
+            Err(CraneError::NoSuchStack {
+                step: 1,
+                stack: "0".to_string()
+            })
+        );
+        assert_eq!(
+            simulate(
+                &CrateMover9001,
+                &supplies,
+                &[mv(1, "3", "1"), mv(1, "3", "2")]
+            ),
+            Err(CraneError::NotEnoughCrates {
+                step: 2,
+                stack: "3".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn trace_test() {
+        let (supplies, moves) = input_generator(SAMPLE).unwrap();
+        let frames: Vec<String> = trace(&CrateMover9000, &supplies, &moves)
+            .unwrap()
+            .iter()
+            .map(Supplies::render)
+            .collect();
+
+        assert_eq!(supplies.render(), SAMPLE.split_once("\n\n").unwrap().0);
+        assert_eq!(frames.len(), 4);
+        assert_eq!(
+            frames[0],
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 "
+        );
+        assert_eq!(
+            frames[3],
+            "        [Z]\n        [N]\n        [D]\n[C] [M] [P]\n 1   2   3 "
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let drawings = [
+            SAMPLE.split_once("\n\n").unwrap().0,
+            include_str!("../input/2022/day5.txt").split_once("\n\n").unwrap().0,
+            // trailing spaces trimmed, the way the puzzle text shows it
+            "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3",
+            // more than 9 stacks, with an empty one
+            "                                        [K] \n[A] [B] [C] [D] [E] [F] [G] [H] [I]     [J] \n 1   2   3   4   5   6   7   8   9   10  11 ",
+            // wider columns and crates
+            "     [XY]\n[AB] [CD]       [E]\n  a    b    c     d",
+        ];
+
+        for drawing in drawings {
+            assert_eq!(drawing.parse::<Supplies>().unwrap().render(), drawing);
+        }
+    }
 
-        assert_eq!(part2(&input_generator(input)), "MCD");
+    #[test]
+    fn wide_drawings() {
+        let drawing = "[A] [B] [C] [D] [E] [F] [G] [H] [I]     [J] \n 1   2   3   4   5   6   7   8   9   10  11 ";
+        let supplies: Supplies = drawing.parse().unwrap();
+
+        assert_eq!(supplies.stack("10"), Some(&Vec::new()));
+        assert_eq!(supplies.stack("11"), Some(&vec!["J".to_string()]));
+
+        let moved = simulate(&CrateMover9000, &supplies, &[mv(1, "11", "10")]).unwrap();
+
+        assert_eq!(
+            moved.render(),
+            "[A] [B] [C] [D] [E] [F] [G] [H] [I] [J]     \n 1   2   3   4   5   6   7   8   9   10  11 "
+        );
+
+        let supplies: Supplies = "     [XY]\n[AB] [CD]       [E]\n  a    b    c     d"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            supplies.stacks(),
+            stacks(&[&["AB"], &["CD", "XY"], &[], &["E"]])
+        );
+
+        let moved = simulate(&CrateMover9001, &supplies, &[mv(2, "b", "c")]).unwrap();
+
+        assert_eq!(moved.top_crates(), "ABXYE");
+        assert_eq!(
+            moved.render(),
+            "          [XY]\n[AB]      [CD]  [E]\n  a    b    c     d"
+        );
+        assert_eq!(
+            "[A\n 1".parse::<Supplies>(),
+            Err(CraneError::InvalidDrawing { line: 1 })
+        );
     }
 }
@@ -45,37 +45,165 @@ zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw: first marker after character 26
 How many characters need to be processed before the first start-of-message marker is detected?
 */
 
-#[aoc_generator(day6)]
-pub fn input_generator(input: &str) -> Vec<char> {
-    input.chars().collect()
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+const BUFFER_SIZE: usize = 8192;
+
+#[derive(Debug, PartialEq)]
+pub enum MarkerError {
+    EmptyMarker, // a marker length of 0, which every position would match
 }
 
-#[aoc(day6, part1)]
-pub fn part1(input: &Vec<char>) -> usize {
-    find_marker(input, 4).expect("Could not find signal start!")
+impl Error for MarkerError {}
+
+impl fmt::Display for MarkerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarkerError::EmptyMarker => {
+                write!(f, "Error creating detector, markers can't be empty")
+            }
+        }
+    }
 }
 
-#[aoc(day6, part2)]
-pub fn part2(input: &Vec<char>) -> usize {
-    find_marker(input, 14).expect("Could not find signal start!")
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Marker {
+    pub length: usize,
+    pub offset: usize, // bytes read by the time the marker is complete, which is the puzzle's answer
 }
 
-fn find_marker(chars: &Vec<char>, count: usize) -> Option<usize> {
-    chars.windows(count).enumerate().find_map(|(i, chars)| {
-        let mut seen = [false; 52]; // 🤷 52 should be fine, right?
+// byte counts for the last `length` bytes, and how many byte values show up more than once
+#[derive(Debug, Clone)]
+struct Window {
+    length: usize,
+    counts: [u32; 256],
+    repeated: usize,
+}
 
-        for c in chars {
-            let ci = (*c as usize) - 'Z' as usize;
+/**
+ * Finds every run of distinct bytes of each of the given lengths. Each byte
+ * is added to every window and the byte that falls out of it is removed, so
+ * the whole stream takes O(n) per length and only the longest window's worth
+ * of history is ever kept.
+ */
+#[derive(Debug, Clone)]
+pub struct MarkerDetector {
+    windows: Vec<Window>,
+    history: VecDeque<u8>,
+    longest: usize,
+    offset: usize,
+}
 
-            if seen[ci] {
-                return None;
+impl MarkerDetector {
+    pub fn new(lengths: &[usize]) -> Result<Self, MarkerError> {
+        if lengths.contains(&0) {
+            return Err(MarkerError::EmptyMarker);
+        }
+
+        let longest = lengths.iter().copied().max().unwrap_or(0);
+
+        Ok(MarkerDetector {
+            windows: lengths
+                .iter()
+                .map(|&length| Window {
+                    length,
+                    counts: [0; 256],
+                    repeated: 0,
+                })
+                .collect(),
+            history: VecDeque::with_capacity(longest + 1),
+            longest,
+            offset: 0,
+        })
+    }
+
+    // pushes a single byte, calling `found` for every marker it completes
+    pub fn push(&mut self, byte: u8, mut found: impl FnMut(Marker)) {
+        self.history.push_back(byte);
+        self.offset += 1;
+
+        for window in self.windows.iter_mut() {
+            window.counts[byte as usize] += 1;
+
+            if window.counts[byte as usize] == 2 {
+                window.repeated += 1;
             }
 
-            seen[ci] = true;
+            // the byte that just slid out of this window, if it's full
+            if self.history.len() > window.length {
+                let old = self.history[self.history.len() - 1 - window.length] as usize;
+
+                window.counts[old] -= 1;
+
+                if window.counts[old] == 1 {
+                    window.repeated -= 1;
+                }
+            }
+
+            if self.offset >= window.length && window.repeated == 0 {
+                found(Marker {
+                    length: window.length,
+                    offset: self.offset,
+                });
+            }
         }
 
-        Some(i + count)
-    })
+        if self.history.len() > self.longest {
+            self.history.pop_front();
+        }
+    }
+
+    // every marker in the stream, in the order they complete
+    pub fn detect(mut self, mut reader: impl Read) -> io::Result<Vec<Marker>> {
+        let mut buffer = [0; BUFFER_SIZE];
+        let mut markers = Vec::new();
+
+        loop {
+            let n = match reader.read(&mut buffer) {
+                Ok(0) => return Ok(markers),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            for &byte in &buffer[..n] {
+                self.push(byte, |marker| markers.push(marker));
+            }
+        }
+    }
+}
+
+pub fn first_marker(input: &[u8], length: usize) -> Option<usize> {
+    let mut detector = MarkerDetector::new(&[length]).ok()?;
+    let mut first = None;
+
+    for &byte in input {
+        detector.push(byte, |marker| first = Some(marker.offset));
+
+        if first.is_some() {
+            break;
+        }
+    }
+
+    first
+}
+
+#[aoc_generator(day6)]
+pub fn input_generator(input: &str) -> Vec<u8> {
+    input.as_bytes().to_vec()
+}
+
+#[aoc(day6, part1)]
+pub fn part1(input: &[u8]) -> Option<usize> {
+    first_marker(input, 4)
+}
+
+#[aoc(day6, part2)]
+pub fn part2(input: &[u8]) -> Option<usize> {
+    first_marker(input, 14)
 }
 
 #[cfg(test)]
@@ -84,66 +212,139 @@ mod tests {
 
     #[test]
     fn input() {
-        let input = "bvwbjplbgvbhsrlpgdmjqwftvncz";
-        assert_eq!(
-            input_generator(input),
-            [
-                'b', 'v', 'w', 'b', 'j', 'p', 'l', 'b', 'g', 'v', 'b', 'h', 's', 'r', 'l', 'p',
-                'g', 'd', 'm', 'j', 'q', 'w', 'f', 't', 'v', 'n', 'c', 'z'
-            ]
-        );
+        let input = "bvwb";
+        assert_eq!(input_generator(input), [b'b', b'v', b'w', b'b']);
     }
 
     #[test]
     fn sample1() {
         let input = "bvwbjplbgvbhsrlpgdmjqwftvncz";
-        assert_eq!(part1(&input_generator(input)), 5);
+        assert_eq!(part1(&input_generator(input)), Some(5));
     }
 
     #[test]
     fn sample2() {
         let input = "nppdvjthqldpwncqszvftbrmjlhg";
-        assert_eq!(part1(&input_generator(input)), 6);
+        assert_eq!(part1(&input_generator(input)), Some(6));
     }
 
     #[test]
     fn sample3() {
         let input = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
-        assert_eq!(part1(&input_generator(input)), 10);
+        assert_eq!(part1(&input_generator(input)), Some(10));
     }
 
     #[test]
     fn sample4() {
         let input = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw";
-        assert_eq!(part1(&input_generator(input)), 11);
+        assert_eq!(part1(&input_generator(input)), Some(11));
     }
 
     #[test]
     fn sample5() {
         let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
-        assert_eq!(part2(&input_generator(input)), 19);
+        assert_eq!(part2(&input_generator(input)), Some(19));
     }
     #[test]
     fn sample6() {
         let input = "bvwbjplbgvbhsrlpgdmjqwftvncz";
-        assert_eq!(part2(&input_generator(input)), 23);
+        assert_eq!(part2(&input_generator(input)), Some(23));
     }
 
     #[test]
     fn sample7() {
         let input = "nppdvjthqldpwncqszvftbrmjlhg";
-        assert_eq!(part2(&input_generator(input)), 23);
+        assert_eq!(part2(&input_generator(input)), Some(23));
     }
 
     #[test]
     fn sample8() {
         let input = "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg";
-        assert_eq!(part2(&input_generator(input)), 29);
+        assert_eq!(part2(&input_generator(input)), Some(29));
     }
 
     #[test]
     fn sample9() {
         let input = "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw";
-        assert_eq!(part2(&input_generator(input)), 26);
+        assert_eq!(part2(&input_generator(input)), Some(26));
+    }
+
+    #[test]
+    fn part1_test() {
+        let input = input_generator(include_str!("../input/2022/day6.txt").trim());
+        assert_eq!(part1(&input), Some(1640));
+    }
+
+    #[test]
+    fn part2_test() {
+        let input = input_generator(include_str!("../input/2022/day6.txt").trim());
+        assert_eq!(part2(&input), Some(3613));
+    }
+
+    #[test]
+    fn every_marker() {
+        let markers = MarkerDetector::new(&[3, 2])
+            .unwrap()
+            .detect("aabcbb".as_bytes())
+            .unwrap();
+        let offsets = |length| {
+            markers
+                .iter()
+                .filter(|m| m.length == length)
+                .map(|m| m.offset)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(offsets(3), [4]);
+        assert_eq!(offsets(2), [3, 4, 5]);
+        assert_eq!(
+            markers[..2],
+            [
+                Marker {
+                    length: 2,
+                    offset: 3
+                },
+                Marker {
+                    length: 3,
+                    offset: 4
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_marker() {
+        assert_eq!(
+            MarkerDetector::new(&[4, 0]).unwrap_err(),
+            MarkerError::EmptyMarker
+        );
+        assert_eq!(first_marker(b"abcd", 0), None);
+    }
+
+    #[test]
+    fn any_bytes() {
+        // bytes outside the alphabet used to panic, and a stream longer than the read buffer
+        let mut stream = vec![0u8; BUFFER_SIZE * 3];
+
+        stream.extend(250..=255);
+        stream.extend([b'\n', 0, 0]);
+
+        let markers = MarkerDetector::new(&[6, 7, 8])
+            .unwrap()
+            .detect(stream.as_slice())
+            .unwrap();
+        let end = BUFFER_SIZE * 3;
+        let offsets = |length| {
+            markers
+                .iter()
+                .filter(|m| m.length == length)
+                .map(|m| m.offset - end)
+                .collect::<Vec<_>>()
+        };
+
+        // the last of the zeros still counts towards the first few windows
+        assert_eq!(offsets(6), [5, 6, 7, 8]);
+        assert_eq!(offsets(7), [6, 7, 8]);
+        assert_eq!(offsets(8), [7, 8]);
     }
 }
@@ -92,341 +92,783 @@ Directories e and a are both too small; deleting them would not free up enough s
 Find the smallest directory that, if deleted, would free up enough space on the filesystem to run the update. What is the total size of that directory?
 */
 
-use itertools::Itertools;
-use std::{
-    cell::RefCell,
-    collections::HashMap,
-    fmt::{self, Display, Formatter},
-    rc::Rc,
-};
-use uuid::Uuid;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+// index of an entry in the file system's arena, handed out in creation order
+pub type EntryId = usize;
+
+// the root directory is always the first entry
+const ROOT: EntryId = 0;
+
+const DISK_SIZE: usize = 70000000;
+const SPACE_NEEDED: usize = 30000000;
+const SMALL_DIR_SIZE: usize = 100000;
 
 #[derive(Debug, PartialEq)]
-enum EntryKind {
+pub enum FsError {
+    NoSuchEntry { path: String },
+    NotADirectory { path: String },
+    AlreadyExists { path: String },
+    RemoveRoot,
+    RemoveWorkingDirectory { path: String },
+    MoveIntoItself { path: String },
+}
+
+impl Error for FsError {}
+
+impl Display for FsError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FsError::NoSuchEntry { path } => write!(f, "Error finding `{}`", path),
+            FsError::NotADirectory { path } => write!(f, "Error `{}` is not a directory", path),
+            FsError::AlreadyExists { path } => write!(f, "Error `{}` already exists", path),
+            FsError::RemoveRoot => write!(f, "Error removing the root directory"),
+            FsError::RemoveWorkingDirectory { path } => {
+                write!(
+                    f,
+                    "Error removing `{}`, it contains the working directory",
+                    path
+                )
+            }
+            FsError::MoveIntoItself { path } => {
+                write!(f, "Error moving `{}` inside itself", path)
+            }
+        }
+    }
+}
+
+// `line` is the 1-based line of the transcript the error was found on
+#[derive(Debug, PartialEq)]
+pub enum TranscriptError {
+    UnknownCommand {
+        line: usize,
+        command: String,
+    },
+    MissingArgument {
+        line: usize,
+        command: String,
+    },
+    InvalidListing {
+        line: usize,
+    },
+    UnexpectedListing {
+        line: usize,
+    },
+    ListingConflict {
+        line: usize,
+        path: String,
+        known: String,
+        listed: String,
+    },
+    MissingFromListing {
+        line: usize,
+        path: String,
+    },
+    Fs {
+        line: usize,
+        error: FsError,
+    },
+}
+
+impl Error for TranscriptError {}
+
+impl Display for TranscriptError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            TranscriptError::UnknownCommand { line, command } => {
+                write!(f, "Error on line {}, unknown command `{}`", line, command)
+            }
+            TranscriptError::MissingArgument { line, command } => {
+                write!(
+                    f,
+                    "Error on line {}, `{}` is missing an argument",
+                    line, command
+                )
+            }
+            TranscriptError::InvalidListing { line } => {
+                write!(f, "Error parsing listing on line {}", line)
+            }
+            TranscriptError::UnexpectedListing { line } => {
+                write!(f, "Error on line {}, listing without an `ls`", line)
+            }
+            TranscriptError::ListingConflict {
+                line,
+                path,
+                known,
+                listed,
+            } => write!(
+                f,
+                "Error on line {}, `{}` is listed as ({}) but was seen as ({})",
+                line, path, listed, known
+            ),
+            TranscriptError::MissingFromListing { line, path } => write!(
+                f,
+                "Error on line {}, `{}` is missing from the listing",
+                line, path
+            ),
+            TranscriptError::Fs { line, error } => write!(f, "{} on line {}", error, line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
     File,
     Dir,
 }
 
 #[derive(Debug)]
 pub struct Entry {
-    id: Uuid,
-    parent: Option<Rc<RefCell<Entry>>>,
+    id: EntryId,
+    parent: Option<EntryId>,
     name: String,
     kind: EntryKind,
-    ext: Option<String>, // eot, jpg, txt
-    size: usize,
-    children: Vec<Rc<RefCell<Entry>>>,
-}
-
-#[derive(Debug)]
-pub struct FileSystem {
-    cwd: Option<Rc<RefCell<Entry>>>,
-    entries: HashMap<Uuid, Rc<RefCell<Entry>>>,
+    size: usize, // only set for files, directories add up their children
+    children: Vec<EntryId>,
+    cached_size: Cell<Option<usize>>,
 }
 
 impl Entry {
-    pub fn new_dir(parent: Option<Rc<RefCell<Entry>>>, name: String) -> Self {
-        Entry {
-            id: Uuid::new_v4(),
-            parent,
-            name,
-            kind: EntryKind::Dir,
-            ext: None,
-            size: 0,
-            children: vec![],
-        }
+    pub fn id(&self) -> EntryId {
+        self.id
     }
 
-    pub fn new_file(
-        parent: Option<Rc<RefCell<Entry>>>,
-        name: String,
-        ext: Option<String>,
-        size: usize,
-    ) -> Self {
-        Entry {
-            id: Uuid::new_v4(),
-            parent,
-            name,
-            kind: EntryKind::File,
-            ext,
-            size,
-            children: vec![],
-        }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> EntryKind {
+        self.kind
     }
 
-    fn is_file(&self) -> bool {
+    pub fn parent(&self) -> Option<EntryId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[EntryId] {
+        &self.children
+    }
+
+    // eot, jpg, txt
+    pub fn ext(&self) -> Option<&str> {
+        self.name.split_once('.').map(|(_, ext)| ext)
+    }
+
+    pub fn is_file(&self) -> bool {
         self.kind == EntryKind::File
     }
 
-    fn is_dir(&self) -> bool {
+    pub fn is_dir(&self) -> bool {
         self.kind == EntryKind::Dir
     }
 }
 
-impl Display for Entry {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self.kind {
-            EntryKind::Dir => {
-                write!(
-                    f,
-                    "/{} ({}), [{}]",
-                    self.name,
-                    file_size_entry(self),
-                    self.id
-                )
-            }
-            EntryKind::File => {
-                if let Some(ext) = &self.ext {
-                    write!(f, "{}.{} ({}) [{}]", self.name, ext, self.size, self.id)
-                } else {
-                    write!(f, "{} ({}) [{}]", self.name, self.size, self.id)
-                }
-            }
-        }
+/**
+ * Entries live in a single Vec and refer to each other by index, so ids are
+ * the same from one run to the next and the root is always at index 0.
+ * Directory sizes are worked out on demand and cached, adding a file clears
+ * the cache for every directory above it.
+ */
+#[derive(Debug)]
+pub struct FileSystem {
+    entries: Vec<Entry>,
+    cwd: EntryId,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        FileSystem::new()
     }
 }
 
 impl FileSystem {
     pub fn new() -> Self {
         FileSystem {
-            cwd: None,
-            entries: HashMap::new(),
+            entries: vec![Entry {
+                id: ROOT,
+                parent: None,
+                name: "/".to_string(),
+                kind: EntryKind::Dir,
+                size: 0,
+                children: vec![],
+                cached_size: Cell::new(None),
+            }],
+            cwd: ROOT,
+        }
+    }
+
+    pub fn root(&self) -> EntryId {
+        ROOT
+    }
+
+    pub fn cwd(&self) -> EntryId {
+        self.cwd
+    }
+
+    pub fn get(&self, id: EntryId) -> &Entry {
+        &self.entries[id]
+    }
+
+    // every entry reachable from the root, parents before their children
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        let mut ids = vec![];
+        let mut stack = vec![ROOT];
+
+        while let Some(id) = stack.pop() {
+            ids.push(id);
+            stack.extend(self.entries[id].children.iter().rev());
         }
+
+        ids.into_iter().map(|id| &self.entries[id])
     }
 
-    fn get_cwd(&self) -> Rc<RefCell<Entry>> {
-        self.cwd.as_ref().unwrap().clone()
+    pub fn cd(&mut self, id: EntryId) -> Result<(), FsError> {
+        self.ensure_dir(id)?;
+        self.cwd = id;
+
+        Ok(())
     }
 
-    fn get_root(&self) -> Option<Rc<RefCell<Entry>>> {
-        self.entries
-            .values()
-            .into_iter()
-            .find(|&entry| entry.borrow().name == "/")
-            .cloned()
+    pub fn child(&self, dir: EntryId, name: &str) -> Option<EntryId> {
+        self.entries[dir]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.entries[child].name == name)
     }
 
-    fn cd(&mut self, entry: &Rc<RefCell<Entry>>) {
-        let e = entry.borrow();
+    pub fn add_dir(&mut self, parent: EntryId, name: &str) -> Result<EntryId, FsError> {
+        self.add(parent, name, EntryKind::Dir, 0)
+    }
 
-        if e.is_dir() {
-            self.cwd = Some(entry.clone());
+    pub fn add_file(
+        &mut self,
+        parent: EntryId,
+        name: &str,
+        size: usize,
+    ) -> Result<EntryId, FsError> {
+        self.add(parent, name, EntryKind::File, size)
+    }
+
+    /**
+     * Detaches an entry (and everything under it) from its parent. The entry
+     * keeps its slot in the arena so other ids don't shift, it just can't be
+     * reached from the root anymore.
+     */
+    pub fn remove(&mut self, id: EntryId) -> Result<(), FsError> {
+        let parent = self.entries[id].parent.ok_or(FsError::RemoveRoot)?;
+
+        if self.contains(id, self.cwd) {
+            return Err(FsError::RemoveWorkingDirectory {
+                path: self.path(id),
+            });
+        }
+
+        self.entries[parent].children.retain(|&child| child != id);
+        self.invalidate(parent);
+
+        Ok(())
+    }
+
+    // moves `id` into `dir` as `name`, which also covers renaming in place
+    pub fn rename(&mut self, id: EntryId, dir: EntryId, name: &str) -> Result<(), FsError> {
+        let parent = self.entries[id].parent.ok_or(FsError::MoveIntoItself {
+            path: self.path(id),
+        })?;
+
+        self.ensure_dir(dir)?;
+
+        if self.contains(id, dir) {
+            return Err(FsError::MoveIntoItself {
+                path: self.path(id),
+            });
+        }
+
+        if let Some(existing) = self.child(dir, name) {
+            return Err(FsError::AlreadyExists {
+                path: self.path(existing),
+            });
+        }
+
+        self.entries[parent].children.retain(|&child| child != id);
+        self.invalidate(parent);
+
+        self.entries[id].parent = Some(dir);
+        self.entries[id].name = name.to_string();
+        self.entries[dir].children.push(id);
+        self.invalidate(dir);
+
+        Ok(())
+    }
+
+    /**
+     * Finds an entry by path, from the root if it starts with `/` and from the
+     * working directory otherwise. `..` goes up a level, but never above the
+     * root.
+     */
+    pub fn resolve(&self, path: &str) -> Option<EntryId> {
+        let start = if path.starts_with('/') {
+            ROOT
         } else {
-            panic!("Cannot `cd` into a file");
+            self.cwd
+        };
+
+        path.split('/')
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .try_fold(start, |id, segment| match segment {
+                ".." => Some(self.entries[id].parent.unwrap_or(ROOT)),
+                name if self.entries[id].is_dir() => self.child(id, name),
+                _ => None,
+            })
+    }
+
+    pub fn path(&self, id: EntryId) -> String {
+        let mut names = vec![];
+        let mut id = id;
+
+        // walk up rather than recurse, transcripts can nest directories very deeply
+        while let Some(parent) = self.entries[id].parent {
+            names.push(self.entries[id].name.as_str());
+            id = parent;
         }
+
+        names.reverse();
+        format!("/{}", names.join("/"))
     }
 
-    fn add(&mut self, entry: Rc<RefCell<Entry>>) {
-        let e = entry.borrow();
+    /**
+     * Works out any missing directory sizes with an explicit stack, visiting
+     * a directory a second time once all of its children are known.
+     */
+    pub fn size(&self, id: EntryId) -> usize {
+        let mut stack = vec![(id, false)];
 
-        match e.kind {
-            // add dir to parent dirs
-            EntryKind::Dir => {
-                if let Some(ref parent) = e.parent {
-                    parent.borrow_mut().children.push(entry.clone());
-                }
+        while let Some((dir, children_known)) = stack.pop() {
+            if self.known_size(dir).is_some() {
+                continue;
             }
-            // add file to parent files
-            EntryKind::File => {
-                if let Some(ref parent) = e.parent {
-                    parent.borrow_mut().children.push(entry.clone());
-                }
+
+            let children = &self.entries[dir].children;
+
+            if children_known {
+                let size = children.iter().filter_map(|&c| self.known_size(c)).sum();
+                self.entries[dir].cached_size.set(Some(size));
+            } else {
+                stack.push((dir, true));
+                stack.extend(children.iter().map(|&child| (child, false)));
             }
         }
 
-        self.entries.insert(entry.borrow().id, entry.clone());
+        self.known_size(id).unwrap_or_default()
     }
-}
 
-pub fn file_size_entry(entry: &Entry) -> usize {
-    match entry.kind {
-        EntryKind::File => entry.size,
-        EntryKind::Dir => entry.children.iter().map(|child| file_size(child)).sum(),
+    // a file's size, or a directory's if it's cached
+    fn known_size(&self, id: EntryId) -> Option<usize> {
+        let entry = &self.entries[id];
+
+        match entry.kind {
+            EntryKind::File => Some(entry.size),
+            EntryKind::Dir => entry.cached_size.get(),
+        }
     }
-}
 
-pub fn file_size(entry: &Rc<RefCell<Entry>>) -> usize {
-    let e = entry.borrow();
-    match e.kind {
-        EntryKind::File => e.size,
-        EntryKind::Dir => e.children.iter().map(|child| file_size(child)).sum(),
+    pub fn dirs(&self) -> impl Iterator<Item = &Entry> {
+        self.entries().filter(|entry| entry.is_dir())
     }
-}
 
-#[aoc_generator(day7)]
-pub fn input_generator(input: &str) -> FileSystem {
-    let mut fs = FileSystem::new();
+    // whether `id` is `ancestor` or somewhere underneath it
+    pub fn contains(&self, ancestor: EntryId, mut id: EntryId) -> bool {
+        loop {
+            if id == ancestor {
+                return true;
+            }
 
-    for line in input.lines() {
-        let mut words = line.split_whitespace().peekable();
+            match self.entries[id].parent {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
 
-        /*
-         * parse command from line
-         *      $ cd <dir>, $ cd ..
-         *      $ ls
-         */
-        if let Some(&"$") = words.peek() {
-            let _ = words.next();
-
-            match words.next() {
-                Some("cd") => {
-                    match words.next() {
-                        // go up
-                        Some("..") => {
-                            fs.cd(fs.get_cwd().borrow().parent.as_ref().unwrap());
-                        }
-
-                        Some("/") => {
-                            let root_dir =
-                                Rc::new(RefCell::new(Entry::new_dir(None, "/".to_string())));
-
-                            fs.add(root_dir.clone());
-                            fs.cd(&root_dir);
-                        }
-
-                        // cd into dir (and create it if not pre-existing)
-                        Some(name) => {
-                            let cwd = fs.get_cwd();
-                            let current_dir = cwd.borrow();
-
-                            match current_dir.kind {
-                                EntryKind::Dir => {
-                                    for dir in current_dir.children.iter() {
-                                        if dir.borrow().is_dir() && dir.borrow().name == name {
-                                            fs.cd(dir);
-                                            break;
-                                        }
-                                    }
-                                }
-                                _ => panic!("Unexpectedly got a File for `cwd`"),
-                            }
-                        }
-
-                        None => panic!("Expected directory name to `cd` into"),
-                    }
-                }
+    fn ensure_dir(&self, id: EntryId) -> Result<(), FsError> {
+        if self.entries[id].is_dir() {
+            Ok(())
+        } else {
+            Err(FsError::NotADirectory {
+                path: self.path(id),
+            })
+        }
+    }
 
-                // list cwd contents
-                Some("ls") => {
-                    // do nothing
-                }
+    fn add(
+        &mut self,
+        parent: EntryId,
+        name: &str,
+        kind: EntryKind,
+        size: usize,
+    ) -> Result<EntryId, FsError> {
+        self.ensure_dir(parent)?;
 
-                // unrecognized cmd
-                Some(cmd) => panic!("{}", format!("Unrecognized command, '{}'!", cmd)),
+        if let Some(existing) = self.child(parent, name) {
+            return Err(FsError::AlreadyExists {
+                path: self.path(existing),
+            });
+        }
+
+        let id = self.entries.len();
+
+        self.entries.push(Entry {
+            id,
+            parent: Some(parent),
+            name: name.to_string(),
+            kind,
+            size,
+            children: vec![],
+            cached_size: Cell::new(None),
+        });
+        self.entries[parent].children.push(id);
+        self.invalidate(parent);
 
-                // unexpected end of input
-                _ => panic!("Invalid input line"),
+        Ok(id)
+    }
+
+    // sizes are only ever cached bottom up, so above an uncached directory nothing is cached
+    fn invalidate(&self, mut id: EntryId) {
+        loop {
+            if self.entries[id].cached_size.take().is_none() {
+                break;
             }
 
-            continue;
+            match self.entries[id].parent {
+                Some(parent) => id = parent,
+                None => break,
+            }
         }
+    }
+}
+
+// the tree the way the puzzle draws it, children sorted by name
+impl Display for FileSystem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut stack = vec![(ROOT, 0)];
+
+        while let Some((id, depth)) = stack.pop() {
+            let entry = &self.entries[id];
+            let kind = match entry.kind {
+                EntryKind::Dir => "dir",
+                EntryKind::File => "file",
+            };
+
+            writeln!(
+                f,
+                "{}- {} ({}, size={})",
+                "  ".repeat(depth),
+                entry.name,
+                kind,
+                self.size(id)
+            )?;
+
+            let mut children = entry.children.clone();
+            children.sort_by(|&a, &b| self.entries[b].name.cmp(&self.entries[a].name));
+
+            // reverse order, so the first child by name comes off the stack first
+            stack.extend(children.into_iter().map(|child| (child, depth + 1)));
+        }
+
+        Ok(())
+    }
+}
+
+enum Command<'a> {
+    Cd(&'a str),
+    Ls(Option<&'a str>),
+    Mkdir(&'a str),
+    Rm(&'a str),
+    Mv(&'a str, &'a str),
+}
+
+#[derive(Clone, Copy)]
+enum Listing<'a> {
+    Dir(&'a str),
+    File(&'a str, usize),
+}
 
+enum Line<'a> {
+    Command(Command<'a>),
+    Listing(Listing<'a>),
+}
+
+fn parse_line(line: &str, number: usize) -> Result<Line<'_>, TranscriptError> {
+    let mut words = line.split_whitespace();
+
+    match words.next() {
         /*
-         * cwd contains a directory with the specified name
-         *      dir <dir>
+         * parse command from line
+         *      $ cd <path>
+         *      $ ls [path]
+         *      $ mkdir <path>
+         *      $ rm <path>
+         *      $ mv <from> <to>
          */
-        if let Some(&"dir") = words.peek() {
-            let _ = words.next();
-
-            if let Some(name) = words.next() {
-                let cwd = fs.get_cwd();
-                let dir_entry = Rc::new(RefCell::new(Entry::new_dir(Some(cwd), name.to_string())));
+        Some("$") => {
+            let command = words.next().unwrap_or_default();
+            let missing = || TranscriptError::MissingArgument {
+                line: number,
+                command: command.to_string(),
+            };
 
-                fs.add(dir_entry);
-            }
+            let command = match command {
+                "cd" => Command::Cd(words.next().ok_or_else(missing)?),
+                "ls" => Command::Ls(words.next()),
+                "mkdir" => Command::Mkdir(words.next().ok_or_else(missing)?),
+                "rm" => Command::Rm(words.next().ok_or_else(missing)?),
+                "mv" => Command::Mv(
+                    words.next().ok_or_else(missing)?,
+                    words.next().ok_or_else(missing)?,
+                ),
+                _ => {
+                    return Err(TranscriptError::UnknownCommand {
+                        line: number,
+                        command: command.to_string(),
+                    })
+                }
+            };
 
-            continue;
+            Ok(Line::Command(command))
         }
 
         /*
-         * cwd has a file with the specified name and size
+         * the directory being listed contains a directory
+         *      dir <name>
+         */
+        Some("dir") => words
+            .next()
+            .map(|name| Line::Listing(Listing::Dir(name)))
+            .ok_or(TranscriptError::InvalidListing { line: number }),
+
+        /*
+         * the directory being listed has a file with the specified name and size
          *      <size> <name>
          */
-        if let Some(_) = words.peek() {
-            let size = words
-                .next()
-                .expect("Invalid file")
-                .parse::<usize>()
-                .expect("Invalid file size");
-
-            let full_name = words.next().expect("Invalid file name");
-
-            let (filename, kind) = if let Some(_) = full_name.find(".") {
-                let (filename, ext) = full_name.split_once(".").expect("Invalid file name");
-                (filename.to_string(), Some(ext.to_string()))
-            } else {
-                (full_name.to_string(), None)
-            };
-            let cwd = fs.get_cwd();
-            let file_entry = Rc::new(RefCell::new(Entry::new_file(
-                Some(cwd),
-                filename,
-                kind,
-                size,
-            )));
+        Some(size) => {
+            let size = size.parse::<usize>().ok();
 
-            fs.add(file_entry);
+            match (size, words.next()) {
+                (Some(size), Some(name)) => Ok(Line::Listing(Listing::File(name, size))),
+                _ => Err(TranscriptError::InvalidListing { line: number }),
+            }
         }
+
+        None => Err(TranscriptError::InvalidListing { line: number }),
     }
+}
 
-    fs
+fn describe(fs: &FileSystem, id: EntryId) -> String {
+    match fs.get(id).kind() {
+        EntryKind::Dir => "dir".to_string(),
+        EntryKind::File => format!("file, size={}", fs.size(id)),
+    }
 }
 
-#[aoc(day7, part1)]
-pub fn part1(fs: &FileSystem) -> usize {
-    fs.entries
-        .values()
-        .filter_map(|entry| {
-            if entry.borrow().is_file() {
-                return None;
+// the directory an `ls` is listing, and the entries it has listed so far
+struct Ls {
+    line: usize,
+    dir: EntryId,
+    listed: HashSet<EntryId>,
+}
+
+impl Ls {
+    // a directory that's listed again has to match what was seen before
+    fn finish(self, fs: &FileSystem) -> Result<(), TranscriptError> {
+        match fs
+            .get(self.dir)
+            .children()
+            .iter()
+            .find(|child| !self.listed.contains(child))
+        {
+            Some(&missing) => Err(TranscriptError::MissingFromListing {
+                line: self.line,
+                path: fs.path(missing),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/**
+ * Replays a terminal transcript against the file system. Listings fill in
+ * entries the first time a directory is seen and are checked against what's
+ * already known after that, so repeated `ls` output is never counted twice.
+ */
+pub fn run(fs: &mut FileSystem, transcript: &str) -> Result<(), TranscriptError> {
+    let mut ls: Option<Ls> = None;
+
+    for (i, line) in transcript.lines().enumerate() {
+        let number = i + 1;
+        let fs_error = |error| TranscriptError::Fs {
+            line: number,
+            error,
+        };
+
+        let command = match parse_line(line, number)? {
+            Line::Listing(listing) => {
+                let ls = ls
+                    .as_mut()
+                    .ok_or(TranscriptError::UnexpectedListing { line: number })?;
+
+                let (name, listed) = match listing {
+                    Listing::Dir(name) => (name, "dir".to_string()),
+                    Listing::File(name, size) => (name, format!("file, size={}", size)),
+                };
+
+                let id = match (fs.child(ls.dir, name), listing) {
+                    (Some(id), _) if describe(fs, id) == listed => id,
+                    (Some(id), _) => {
+                        return Err(TranscriptError::ListingConflict {
+                            line: number,
+                            path: fs.path(id),
+                            known: describe(fs, id),
+                            listed,
+                        })
+                    }
+                    (None, Listing::Dir(name)) => fs.add_dir(ls.dir, name).map_err(fs_error)?,
+                    (None, Listing::File(name, size)) => {
+                        fs.add_file(ls.dir, name, size).map_err(fs_error)?
+                    }
+                };
+
+                ls.listed.insert(id);
+                continue;
             }
 
-            let size = file_size(entry);
+            Line::Command(command) => command,
+        };
 
-            if size > 100000 {
-                return None;
+        if let Some(ls) = ls.take() {
+            ls.finish(fs)?;
+        }
+
+        let resolve = |fs: &FileSystem, path: &str| {
+            fs.resolve(path).ok_or_else(|| {
+                fs_error(FsError::NoSuchEntry {
+                    path: path.to_string(),
+                })
+            })
+        };
+
+        match command {
+            Command::Cd(path) => {
+                let dir = resolve(fs, path)?;
+                fs.cd(dir).map_err(fs_error)?;
             }
 
-            Some(size)
-        })
-        .sum()
-}
+            Command::Ls(path) => {
+                let dir = match path {
+                    Some(path) => resolve(fs, path)?,
+                    None => fs.cwd(),
+                };
 
-#[aoc(day7, part2)]
-pub fn part2(fs: &FileSystem) -> usize {
-    let hdd_size = 70000000;
-    let space_needed = 30000000;
-    let used_size = file_size(&fs.get_root().unwrap());
-    let free = hdd_size - used_size;
-    let min_size_to_free = space_needed - free;
-
-    fs.entries
-        .values()
-        .filter_map(|entry| {
-            if entry.borrow().is_file() {
-                return None;
+                if !fs.get(dir).is_dir() {
+                    return Err(fs_error(FsError::NotADirectory { path: fs.path(dir) }));
+                }
+
+                ls = Some(Ls {
+                    line: number,
+                    dir,
+                    listed: HashSet::new(),
+                });
+            }
+
+            Command::Mkdir(path) => {
+                if let Some(existing) = fs.resolve(path) {
+                    return Err(fs_error(FsError::AlreadyExists {
+                        path: fs.path(existing),
+                    }));
+                }
+
+                let (parent, name) = split_path(path);
+                let parent = resolve(fs, parent)?;
+                fs.add_dir(parent, name).map_err(fs_error)?;
             }
 
-            let size = file_size(entry);
+            Command::Rm(path) => {
+                let id = resolve(fs, path)?;
+                fs.remove(id).map_err(fs_error)?;
+            }
+
+            // into `to` if it's an existing directory, otherwise renamed to `to`
+            Command::Mv(from, to) => {
+                let id = resolve(fs, from)?;
+
+                let (dir, name) = match fs.resolve(to) {
+                    Some(dir) if fs.get(dir).is_dir() => (dir, fs.get(id).name().to_string()),
+                    Some(existing) => {
+                        return Err(fs_error(FsError::AlreadyExists {
+                            path: fs.path(existing),
+                        }))
+                    }
+                    None => {
+                        let (parent, name) = split_path(to);
+                        (resolve(fs, parent)?, name.to_string())
+                    }
+                };
 
-            if size < min_size_to_free {
-                return None;
+                fs.rename(id, dir, &name).map_err(fs_error)?;
             }
+        }
+    }
+
+    match ls {
+        Some(ls) => ls.finish(fs),
+        None => Ok(()),
+    }
+}
+
+// "/a/b" -> ("/a", "b"), "b" -> (".", "b"), "/b" -> ("/", "b")
+fn split_path(path: &str) -> (&str, &str) {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((parent, name)) => (parent, name),
+        None => (".", path.trim_end_matches('/')),
+    }
+}
+
+#[aoc_generator(day7)]
+pub fn input_generator(input: &str) -> Result<FileSystem, TranscriptError> {
+    let mut fs = FileSystem::new();
+    run(&mut fs, input)?;
+
+    Ok(fs)
+}
+
+#[aoc(day7, part1)]
+pub fn part1(fs: &FileSystem) -> usize {
+    fs.dirs()
+        .map(|dir| fs.size(dir.id()))
+        .filter(|&size| size <= SMALL_DIR_SIZE)
+        .sum()
+}
 
-            Some(size)
-        })
-        .sorted()
-        .nth(0)
-        .expect("Could not find a suitable directory to delete")
+#[aoc(day7, part2)]
+pub fn part2(fs: &FileSystem) -> Option<usize> {
+    // a tree bigger than the disk can't be made to fit
+    let free = DISK_SIZE.checked_sub(fs.size(fs.root()))?;
+    let min_size_to_free = SPACE_NEEDED.saturating_sub(free);
+
+    fs.dirs()
+        .map(|dir| fs.size(dir.id()))
+        .filter(|&size| size >= min_size_to_free)
+        .min()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn input() {
-        let input = "$ cd /
+    const SAMPLE: &str = "$ cd /
 $ ls
 dir a
 14848514 b.txt
@@ -449,68 +891,281 @@ $ ls
 8033020 d.log
 5626152 d.ext
 7214296 k";
-        let fs = input_generator(input);
 
-        assert_eq!(fs.entries.len(), 14);
+    #[test]
+    fn input() {
+        let fs = input_generator(SAMPLE).unwrap();
 
-        let root = fs.get_root().expect("Could not find root dir");
-        assert_eq!(file_size(&root), 48381165);
+        assert_eq!(fs.entries().count(), 14);
+        assert_eq!(fs.size(fs.root()), 48381165);
+        assert_eq!(fs.get(fs.root()).name(), "/");
     }
 
     #[test]
     fn sample1() {
-        let input = "$ cd /
-$ ls
-dir a
-14848514 b.txt
-8504156 c.dat
-dir d
-$ cd a
-$ ls
-dir e
-29116 f
-2557 g
-62596 h.lst
-$ cd e
-$ ls
-584 i
-$ cd ..
-$ cd ..
-$ cd d
-$ ls
-4060174 j
-8033020 d.log
-5626152 d.ext
-7214296 k";
-        let fs = input_generator(input);
+        let fs = input_generator(SAMPLE).unwrap();
         assert_eq!(part1(&fs), 95437);
     }
 
     #[test]
     fn sample2() {
-        let input = "$ cd /
-$ ls
-dir a
-14848514 b.txt
-8504156 c.dat
-dir d
-$ cd a
-$ ls
-dir e
-29116 f
-2557 g
-62596 h.lst
-$ cd e
-$ ls
-584 i
-$ cd ..
-$ cd ..
-$ cd d
-$ ls
-4060174 j
-8033020 d.log
-5626152 d.ext
-7214296 k";
-        assert_eq!(part2(&input_generator(input)), 24933642);
+        assert_eq!(part2(&input_generator(SAMPLE).unwrap()), Some(24933642));
+    }
+
+    #[test]
+    fn resolve() {
+        let mut fs = input_generator(SAMPLE).unwrap();
+        let e = fs.resolve("/a/e").unwrap();
+
+        assert_eq!(fs.get(e).name(), "e");
+        assert_eq!(fs.path(e), "/a/e");
+        assert_eq!(fs.size(e), 584);
+        assert_eq!(fs.resolve("/a/e/../h.lst"), fs.resolve("/a/h.lst"));
+        assert_eq!(fs.get(fs.resolve("/d/d.log").unwrap()).ext(), Some("log"));
+        assert_eq!(fs.resolve("/b.txt/x"), None);
+        assert_eq!(fs.resolve("/nope"), None);
+
+        // relative paths start from the working directory, which ended up in `d`
+        assert_eq!(fs.resolve("k"), fs.resolve("/d/k"));
+
+        fs.cd(e).unwrap();
+
+        assert_eq!(fs.resolve("../../d"), fs.resolve("/d"));
+        assert_eq!(fs.resolve("/"), Some(fs.root()));
+    }
+
+    #[test]
+    fn cached_sizes() {
+        let mut fs = input_generator(SAMPLE).unwrap();
+        let a = fs.resolve("/a").unwrap();
+
+        assert_eq!(fs.size(a), 94853);
+
+        let e = fs.resolve("/a/e").unwrap();
+        fs.add_file(e, "z", 1000).unwrap();
+
+        assert_eq!(fs.size(e), 1584);
+        assert_eq!(fs.size(a), 95853);
+        assert_eq!(fs.size(fs.root()), 48382165);
+    }
+
+    #[test]
+    fn display() {
+        let fs = input_generator(SAMPLE).unwrap();
+
+        assert_eq!(
+            fs.to_string(),
+            "- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)
+"
+        );
+    }
+
+    #[test]
+    fn cd_paths() {
+        let fs =
+            input_generator(&format!("{}\n$ cd /a/e\n$ cd ../../d/./\n$ cd ..", SAMPLE)).unwrap();
+        assert_eq!(fs.cwd(), fs.root());
+
+        let fs = input_generator(&format!("{}\n$ cd /a/e", SAMPLE)).unwrap();
+        assert_eq!(fs.path(fs.cwd()), "/a/e");
+    }
+
+    #[test]
+    fn repeated_ls() {
+        let fs = input_generator(&format!(
+            "{}\n$ ls\n4060174 j\n8033020 d.log\n5626152 d.ext\n7214296 k\n$ ls /a/e\n584 i",
+            SAMPLE
+        ))
+        .unwrap();
+
+        assert_eq!(fs.entries().count(), 14);
+        assert_eq!(part1(&fs), 95437);
+    }
+
+    #[test]
+    fn inconsistent_listings() {
+        assert_eq!(
+            input_generator(&format!("{}\n$ ls\n4060174 j\n1 d.log", SAMPLE)).unwrap_err(),
+            TranscriptError::ListingConflict {
+                line: 26,
+                path: "/d/d.log".to_string(),
+                known: "file, size=8033020".to_string(),
+                listed: "file, size=1".to_string(),
+            }
+        );
+        assert_eq!(
+            input_generator(&format!("{}\n$ ls /\ndir b.txt", SAMPLE)).unwrap_err(),
+            TranscriptError::ListingConflict {
+                line: 25,
+                path: "/b.txt".to_string(),
+                known: "file, size=14848514".to_string(),
+                listed: "dir".to_string(),
+            }
+        );
+        assert_eq!(
+            input_generator(&format!("{}\n$ ls /a/e\n$ cd /", SAMPLE)).unwrap_err(),
+            TranscriptError::MissingFromListing {
+                line: 24,
+                path: "/a/e/i".to_string(),
+            }
+        );
+        assert_eq!(
+            input_generator("$ cd /\ndir a").unwrap_err(),
+            TranscriptError::UnexpectedListing { line: 2 }
+        );
+        assert_eq!(
+            input_generator("$ ls\nabc def").unwrap_err(),
+            TranscriptError::InvalidListing { line: 2 }
+        );
+    }
+
+    #[test]
+    fn invalid_commands() {
+        assert_eq!(
+            input_generator(&format!("{}\n$ cd /b.txt", SAMPLE)).unwrap_err(),
+            TranscriptError::Fs {
+                line: 24,
+                error: FsError::NotADirectory {
+                    path: "/b.txt".to_string()
+                },
+            }
+        );
+        assert_eq!(
+            input_generator("$ cd /x").unwrap_err(),
+            TranscriptError::Fs {
+                line: 1,
+                error: FsError::NoSuchEntry {
+                    path: "/x".to_string()
+                },
+            }
+        );
+        assert_eq!(
+            input_generator("$ cd").unwrap_err(),
+            TranscriptError::MissingArgument {
+                line: 1,
+                command: "cd".to_string()
+            }
+        );
+        assert_eq!(
+            input_generator("$ pwd").unwrap_err(),
+            TranscriptError::UnknownCommand {
+                line: 1,
+                command: "pwd".to_string()
+            }
+        );
+        assert_eq!(
+            input_generator(&format!("{}\n$ rm /", SAMPLE)).unwrap_err(),
+            TranscriptError::Fs {
+                line: 24,
+                error: FsError::RemoveRoot,
+            }
+        );
+        assert_eq!(
+            input_generator(&format!("{}\n$ rm /d", SAMPLE)).unwrap_err(),
+            TranscriptError::Fs {
+                line: 24,
+                error: FsError::RemoveWorkingDirectory {
+                    path: "/d".to_string()
+                },
+            }
+        );
+        assert_eq!(
+            input_generator(&format!("{}\n$ mv /a /a/e", SAMPLE)).unwrap_err(),
+            TranscriptError::Fs {
+                line: 24,
+                error: FsError::MoveIntoItself {
+                    path: "/a".to_string()
+                },
+            }
+        );
+        assert_eq!(
+            input_generator(&format!("{}\n$ mkdir /a/e", SAMPLE)).unwrap_err(),
+            TranscriptError::Fs {
+                line: 24,
+                error: FsError::AlreadyExists {
+                    path: "/a/e".to_string()
+                },
+            }
+        );
+        assert_eq!(
+            input_generator(&format!("{}\n$ mv /b.txt /c.dat", SAMPLE)).unwrap_err(),
+            TranscriptError::Fs {
+                line: 24,
+                error: FsError::AlreadyExists {
+                    path: "/c.dat".to_string()
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn rm_mv_mkdir() {
+        let fs = input_generator(&format!(
+            "{}\n$ mkdir /x\n$ mkdir ../x/y\n$ mv k /x/y\n$ mv /a/e /x/f\n$ rm /a\n$ cd /x\n$ ls\ndir y\ndir f",
+            SAMPLE
+        ))
+        .unwrap();
+
+        assert_eq!(
+            fs.to_string(),
+            "- / (dir, size=48286896)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=17719346)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+  - x (dir, size=7214880)
+    - f (dir, size=584)
+      - i (file, size=584)
+    - y (dir, size=7214296)
+      - k (file, size=7214296)
+"
+        );
+        assert_eq!(fs.entries().count(), 12);
+        assert_eq!(fs.resolve("/a"), None);
+    }
+
+    #[test]
+    fn deep_nesting() {
+        let depth = 100_000;
+        let transcript = "$ mkdir d\n$ cd d\n".repeat(depth) + "$ ls\n5 f";
+        let fs = input_generator(&transcript).unwrap();
+
+        assert_eq!(fs.size(fs.root()), 5);
+        assert_eq!(fs.path(fs.cwd()).len(), depth * 2);
+        assert_eq!(part1(&fs), 5 * (depth + 1));
+    }
+
+    #[test]
+    fn larger_than_disk() {
+        let fs = input_generator("$ ls\n70000001 big").unwrap();
+
+        assert_eq!(part2(&fs), None);
+    }
+
+    #[test]
+    fn part1_test() {
+        let fs = input_generator(include_str!("../input/2022/day7.txt").trim()).unwrap();
+        assert_eq!(part1(&fs), 1449447);
+    }
+
+    #[test]
+    fn part2_test() {
+        let fs = input_generator(include_str!("../input/2022/day7.txt").trim()).unwrap();
+        assert_eq!(part2(&fs), Some(8679207));
     }
 }
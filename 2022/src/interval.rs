@@ -0,0 +1,338 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+
+// the integer types an interval can be made of
+pub trait Integer: Copy + Ord + fmt::Debug {
+    fn checked_next(self) -> Option<Self>;
+    fn checked_prev(self) -> Option<Self>;
+    fn distance(lo: Self, hi: Self) -> u128; // `hi - lo`, which must not be negative
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                fn checked_next(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn checked_prev(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+
+                fn distance(lo: Self, hi: Self) -> u128 {
+                    (hi as i128 - lo as i128) as u128
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+// an inclusive range of integers, `start` is never after `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Integer> Interval<T> {
+    pub fn new(start: T, end: T) -> Option<Self> {
+        (start <= end).then_some(Interval { start, end })
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    pub fn len(&self) -> u128 {
+        T::distance(self.start, self.end) + 1
+    }
+
+    // an interval always holds at least one value
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    pub fn covers(&self, other: &Interval<T>) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    // overlapping or directly next to each other, so the two make one interval
+    fn touches(&self, other: &Interval<T>) -> bool {
+        self.overlaps(other)
+            || self.end.checked_next() == Some(other.start)
+            || other.end.checked_next() == Some(self.start)
+    }
+}
+
+/**
+ * A set of integers stored as sorted, disjoint intervals. Intervals that
+ * overlap or touch are merged, so every set has exactly one representation.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: Integer> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet {
+            intervals: Vec::new(),
+        }
+    }
+}
+
+impl<T: Integer> FromIterator<Interval<T>> for IntervalSet<T> {
+    // O(n log n), for the sort
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(intervals: I) -> Self {
+        let mut sorted: Vec<Interval<T>> = intervals.into_iter().collect();
+        let mut merged: Vec<Interval<T>> = Vec::with_capacity(sorted.len());
+
+        sorted.sort_unstable_by_key(|interval| interval.start);
+
+        for interval in sorted {
+            match merged.last_mut() {
+                Some(last) if last.touches(&interval) => last.end = last.end.max(interval.end),
+                _ => merged.push(interval),
+            }
+        }
+
+        IntervalSet { intervals: merged }
+    }
+}
+
+impl<T: Integer> From<Interval<T>> for IntervalSet<T> {
+    fn from(interval: Interval<T>) -> Self {
+        IntervalSet {
+            intervals: vec![interval],
+        }
+    }
+}
+
+impl<T: Integer> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet::default()
+    }
+
+    pub fn intervals(&self) -> &[Interval<T>] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let i = self
+            .intervals
+            .partition_point(|interval| interval.end < value);
+
+        self.intervals
+            .get(i)
+            .is_some_and(|interval| interval.contains(value))
+    }
+
+    // number of integers in the set
+    pub fn coverage(&self) -> u128 {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        self.intervals
+            .iter()
+            .chain(other.intervals.iter())
+            .copied()
+            .collect()
+    }
+
+    pub fn intersection(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let (mut i, mut j) = (0, 0);
+        let mut intervals = Vec::new();
+
+        while let (Some(a), Some(b)) = (self.intervals.get(i), other.intervals.get(j)) {
+            if let Some(overlap) = Interval::new(a.start.max(b.start), a.end.min(b.end)) {
+                intervals.push(overlap);
+            }
+
+            // whichever ends first can't overlap anything else on the other side
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        IntervalSet { intervals }
+    }
+
+    pub fn difference(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut intervals = Vec::new();
+        let mut j = 0;
+
+        for a in &self.intervals {
+            let mut start = Some(a.start);
+
+            // skip everything in `other` that ends before this interval starts
+            while other.intervals.get(j).is_some_and(|b| b.end < a.start) {
+                j += 1;
+            }
+
+            let mut k = j;
+
+            while let (Some(s), Some(b)) = (start, other.intervals.get(k)) {
+                if b.start > a.end {
+                    break;
+                }
+
+                if let Some(piece) = b.start.checked_prev().and_then(|e| Interval::new(s, e)) {
+                    intervals.push(piece);
+                }
+
+                start = b.end.checked_next().filter(|&s| s <= a.end);
+                k += 1;
+            }
+
+            if let Some(piece) = start.and_then(|s| Interval::new(s, a.end)) {
+                intervals.push(piece);
+            }
+        }
+
+        IntervalSet { intervals }
+    }
+
+    // the holes between the first and last value in the set
+    pub fn gaps(&self) -> IntervalSet<T> {
+        let intervals = self
+            .intervals
+            .windows(2)
+            .filter_map(|pair| {
+                Interval::new(pair[0].end.checked_next()?, pair[1].start.checked_prev()?)
+            })
+            .collect();
+
+        IntervalSet { intervals }
+    }
+}
+
+/**
+ * Every pair of intervals that overlap, as indices into `intervals` with the
+ * smaller one first, in O(n log n + k) for `k` pairs. Intervals are swept in
+ * order of their start while a heap holds the ones still open, anything left
+ * open when a new interval starts overlaps it.
+ */
+pub fn overlapping_pairs<T: Integer>(intervals: &[Interval<T>]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..intervals.len()).collect();
+    let mut open: BinaryHeap<Reverse<(T, usize)>> = BinaryHeap::new();
+    let mut pairs = Vec::new();
+
+    order.sort_unstable_by_key(|&i| intervals[i].start);
+
+    for i in order {
+        let interval = intervals[i];
+
+        while open
+            .peek()
+            .is_some_and(|Reverse((end, _))| *end < interval.start)
+        {
+            open.pop();
+        }
+
+        pairs.extend(open.iter().map(|&Reverse((_, j))| (i.min(j), i.max(j))));
+        open.push(Reverse((interval.end, i)));
+    }
+
+    pairs.sort_unstable();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(i32, i32)]) -> IntervalSet<i32> {
+        ranges
+            .iter()
+            .map(|&(a, b)| Interval::new(a, b).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            set(&[(5, 7), (1, 2), (3, 3), (6, 10)]),
+            set(&[(1, 3), (5, 10)])
+        );
+        assert_eq!(set(&[(1, 3), (5, 10)]).coverage(), 9);
+        assert!(set(&[(1, 3), (5, 10)]).contains(7));
+        assert!(!set(&[(1, 3), (5, 10)]).contains(4));
+        assert_eq!(Interval::new(3, 2), None);
+    }
+
+    #[test]
+    fn operations() {
+        let a = set(&[(1, 5), (10, 20)]);
+        let b = set(&[(4, 12), (18, 30)]);
+
+        assert_eq!(a.union(&b), set(&[(1, 30)]));
+        assert_eq!(a.intersection(&b), set(&[(4, 5), (10, 12), (18, 20)]));
+        assert_eq!(a.difference(&b), set(&[(1, 3), (13, 17)]));
+        assert_eq!(b.difference(&a), set(&[(6, 9), (21, 30)]));
+        assert_eq!(a.gaps(), set(&[(6, 9)]));
+        assert_eq!(a.difference(&a), IntervalSet::new());
+    }
+
+    #[test]
+    fn extremes() {
+        // no overflow at either end of the type
+        let all: IntervalSet<u8> = Interval::new(0, 255).unwrap().into();
+        let middle: IntervalSet<u8> = Interval::new(1, 254).unwrap().into();
+
+        assert_eq!(all.coverage(), 256);
+        assert_eq!(
+            all.difference(&middle).intervals(),
+            &[
+                Interval::new(0, 0).unwrap(),
+                Interval::new(255, 255).unwrap()
+            ]
+        );
+        assert_eq!(all.difference(&all), IntervalSet::new());
+
+        let wide: IntervalSet<i64> = Interval::new(i64::MIN, i64::MAX).unwrap().into();
+
+        assert_eq!(wide.coverage(), 1 << 64);
+    }
+
+    #[test]
+    fn pairs() {
+        let intervals: Vec<Interval<u64>> = [(5, 9), (1, 3), (3, 4), (10, 12), (2, 11)]
+            .iter()
+            .map(|&(a, b)| Interval::new(a, b).unwrap())
+            .collect();
+        let mut brute_force = Vec::new();
+
+        for i in 0..intervals.len() {
+            for j in i + 1..intervals.len() {
+                if intervals[i].overlaps(&intervals[j]) {
+                    brute_force.push((i, j));
+                }
+            }
+        }
+
+        assert_eq!(overlapping_pairs(&intervals), brute_force);
+    }
+}
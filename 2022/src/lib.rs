@@ -3,6 +3,8 @@ extern crate aoc_runner;
 #[macro_use]
 extern crate aoc_runner_derive;
 
+pub mod interval;
+
 pub mod day1;
 pub mod day2;
 pub mod day3;
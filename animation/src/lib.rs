@@ -0,0 +1,240 @@
+use gif::{Encoder, Repeat};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+// ANSI 256 colour palette reserves 232..=255 for a dark to light grey ramp
+const GREY_RAMP_START: u8 = 232;
+const GREY_RAMP_LEN: u16 = 24;
+
+#[derive(Debug)]
+pub enum AnimationError {
+    Io(io::Error),
+    Gif(gif::EncodingError),
+    Empty,                                       // no frames, or frames without any cells
+    MismatchedFrame(usize), // index of the first frame that isn't the size of the first one
+    FrameSize { expected: usize, found: usize }, // levels given for a `width * height` frame
+    TooLarge,
+}
+
+impl Error for AnimationError {}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnimationError::Io(e) => write!(f, "Error writing animation: {}", e),
+            AnimationError::Gif(e) => write!(f, "Error encoding GIF: {}", e),
+            AnimationError::Empty => {
+                write!(f, "Animation has no frames, or nothing to draw in them")
+            }
+            AnimationError::MismatchedFrame(i) => {
+                write!(f, "Frame {} is a different size than the first frame", i)
+            }
+            AnimationError::FrameSize { expected, found } => write!(
+                f,
+                "Frame needs {} brightness levels but was given {}",
+                expected, found
+            ),
+            AnimationError::TooLarge => write!(f, "Animation is too large for a GIF"),
+        }
+    }
+}
+
+impl From<io::Error> for AnimationError {
+    fn from(e: io::Error) -> Self {
+        AnimationError::Io(e)
+    }
+}
+
+impl From<gif::EncodingError> for AnimationError {
+    fn from(e: gif::EncodingError) -> Self {
+        AnimationError::Gif(e)
+    }
+}
+
+// a single greyscale image, one brightness level per simulated cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub levels: Vec<u8>, // row-major, 0 is black and 255 is white
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize, levels: Vec<u8>) -> Result<Self, AnimationError> {
+        if width == 0 || height == 0 {
+            return Err(AnimationError::Empty);
+        }
+
+        if levels.len() != width * height {
+            return Err(AnimationError::FrameSize {
+                expected: width * height,
+                found: levels.len(),
+            });
+        }
+
+        Ok(Frame {
+            width,
+            height,
+            levels,
+        })
+    }
+
+    // every cell becomes two full blocks, which comes out roughly square in most terminals
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::with_capacity(self.levels.len() * 16);
+
+        for row in self.levels.chunks(self.width.max(1)) {
+            for &level in row {
+                let grey = GREY_RAMP_START as u16 + level as u16 * (GREY_RAMP_LEN - 1) / 255;
+                out.push_str(&format!("\x1b[38;5;{}m██", grey));
+            }
+
+            out.push_str("\x1b[0m\n");
+        }
+
+        out
+    }
+}
+
+/**
+ * Draws each frame over the previous one, waiting `delay` in between. The
+ * caption is printed under each frame, handy for a step counter.
+ */
+pub fn play(
+    frames: impl IntoIterator<Item = (String, Frame)>,
+    delay: Duration,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    // clear the screen once, then only move the cursor home so frames don't flicker
+    write!(out, "\x1b[2J")?;
+
+    for (caption, frame) in frames {
+        writeln!(out, "\x1b[H{}\x1b[K{}", frame.to_ansi(), caption)?;
+        out.flush()?;
+        thread::sleep(delay);
+    }
+
+    Ok(())
+}
+
+// writes a looping GIF where every cell is a `scale` x `scale` block of pixels
+pub fn write_gif(
+    frames: &[Frame],
+    scale: usize,
+    delay: Duration,
+    out: impl Write,
+) -> Result<(), AnimationError> {
+    let first = frames.first().ok_or(AnimationError::Empty)?;
+
+    // frames built by hand can skip the checks in `Frame::new`
+    if first.width == 0 || first.height == 0 {
+        return Err(AnimationError::Empty);
+    }
+
+    if let Some(i) = frames
+        .iter()
+        .position(|frame| (frame.width, frame.height) != (first.width, first.height))
+    {
+        return Err(AnimationError::MismatchedFrame(i));
+    }
+
+    let width = u16::try_from(first.width * scale).map_err(|_| AnimationError::TooLarge)?;
+    let height = u16::try_from(first.height * scale).map_err(|_| AnimationError::TooLarge)?;
+    let palette: Vec<u8> = (0..=255).flat_map(|level| [level, level, level]).collect();
+    let mut encoder = Encoder::new(out, width, height, &palette)?;
+
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    for frame in frames {
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+
+        for row in frame.levels.chunks(frame.width) {
+            let scaled: Vec<u8> = row
+                .iter()
+                .flat_map(|&level| std::iter::repeat_n(level, scale))
+                .collect();
+
+            for _ in 0..scale {
+                pixels.extend_from_slice(&scaled);
+            }
+        }
+
+        encoder.write_frame(&gif::Frame {
+            width,
+            height,
+            delay: (delay.as_millis() / 10).min(u16::MAX as u128) as u16, // GIFs count in 1/100s
+            buffer: Cow::Owned(pixels),
+            ..gif::Frame::default()
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ansi_test() {
+        let frame = Frame::new(2, 1, vec![0, 255]).unwrap();
+
+        assert_eq!(frame.to_ansi(), "\x1b[38;5;232m██\x1b[38;5;255m██\x1b[0m\n");
+        assert!(matches!(
+            Frame::new(2, 2, vec![0, 255]),
+            Err(AnimationError::FrameSize {
+                expected: 4,
+                found: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn gif_test() {
+        let frames = vec![Frame::new(2, 2, vec![0, 255, 255, 0]).unwrap(); 3];
+        let mut bytes = Vec::new();
+
+        write_gif(&frames, 4, Duration::from_millis(100), &mut bytes).unwrap();
+
+        assert_eq!(&bytes[..6], b"GIF89a");
+        assert_eq!(&bytes[6..10], &[8, 0, 8, 0]); // 8x8 canvas, little endian
+        assert_eq!(*bytes.last().unwrap(), 0x3b); // trailer
+
+        let mismatched = vec![
+            Frame::new(1, 1, vec![0]).unwrap(),
+            Frame::new(2, 1, vec![0, 0]).unwrap(),
+        ];
+
+        assert!(matches!(
+            write_gif(&mismatched, 1, Duration::ZERO, Vec::new()),
+            Err(AnimationError::MismatchedFrame(1))
+        ));
+        assert!(matches!(
+            write_gif(&[], 1, Duration::ZERO, Vec::new()),
+            Err(AnimationError::Empty)
+        ));
+
+        let blank = Frame {
+            width: 0,
+            height: 0,
+            levels: vec![],
+        };
+
+        assert!(matches!(
+            write_gif(&[blank], 1, Duration::ZERO, Vec::new()),
+            Err(AnimationError::Empty)
+        ));
+        assert!(matches!(
+            Frame::new(0, 0, vec![]),
+            Err(AnimationError::Empty)
+        ));
+        assert!(matches!(
+            Frame::new(3, 0, vec![]),
+            Err(AnimationError::Empty)
+        ));
+    }
+}